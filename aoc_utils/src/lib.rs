@@ -1,3 +1,5 @@
+mod lowercase;
+
 pub trait MaxResult<V, E> {
     fn max_result(self) -> Option<Result<V, E>>;
 }
@@ -7,9 +9,9 @@ where
     T: std::iter::Iterator<Item = Result<V, E>>,
     V: PartialOrd + Copy,
 {
-    fn max_result(mut self) -> Option<Result<V, E>> {
+    fn max_result(self) -> Option<Result<V, E>> {
         let mut m = None;
-        while let Some(v) = self.next() {
+        for v in self {
             match v {
                 Ok(v) if Some(v) > m => m = Some(v),
                 Err(e) => return Some(Err(e)),
@@ -20,3 +22,230 @@ where
         m.map(Ok)
     }
 }
+
+/// Follows a stream of bytes and tells for each of them whether it ends `w`
+/// bytes that are all different. Instead of the window itself it keeps
+/// where each byte value was seen last, so every byte costs the same
+/// whatever the window size.
+#[derive(Debug, Clone)]
+pub struct DistinctWindow {
+    w: usize,
+    last_seen: [Option<u64>; 256],
+    // where the longest run of distinct bytes up to the current one starts
+    start: u64,
+    position: u64,
+}
+
+impl DistinctWindow {
+    pub fn new(w: usize) -> Self {
+        DistinctWindow {
+            w,
+            last_seen: [None; 256],
+            start: 0,
+            position: 0,
+        }
+    }
+
+    /// Takes the next byte, returns true if it ends a distinct window.
+    pub fn push(&mut self, b: u8) -> bool {
+        if let Some(j) = self.last_seen[b as usize] {
+            self.start = self.start.max(j + 1);
+        }
+        self.last_seen[b as usize] = Some(self.position);
+        self.position += 1;
+
+        self.run() >= self.w as u64
+    }
+
+    /// Number of bytes taken so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Length of the longest run of distinct bytes ending at the last one.
+    pub fn run(&self) -> u64 {
+        self.position - self.start
+    }
+}
+
+/// Finds the first `w` consecutive bytes of `input` that are all different
+/// and returns the position right after them. Lowercase letters take a
+/// faster path, see the `lowercase` module.
+pub fn find_distinct_window(input: &[u8], w: usize) -> Option<usize> {
+    if w == 0 {
+        return Some(0);
+    }
+    if w > 26 {
+        return find_any_window(input, w);
+    }
+
+    let mut search = lowercase::Search::new(w);
+    for (n, block) in input.chunks(lowercase::BLOCK).enumerate() {
+        let offset = n * lowercase::BLOCK;
+        if !lowercase::is_lowercase(block) {
+            // no window ending before the block was distinct, the generic
+            // search picks up with the first one that ends in it
+            let start = offset.saturating_sub(w - 1);
+            return find_any_window(&input[start..], w).map(|end| start + end);
+        }
+        if let Some(i) = search.block(block) {
+            return Some(offset + i + 1);
+        }
+    }
+    None
+}
+
+/// The search without the fast path, for any bytes and window size.
+pub fn find_any_window(input: &[u8], w: usize) -> Option<usize> {
+    if w == 0 {
+        return Some(0);
+    }
+
+    let mut window = DistinctWindow::new(w);
+    input.iter().position(|&b| window.push(b)).map(|i| i + 1)
+}
+
+/// Like `find_distinct_window`, but reads the bytes from `reader` in chunks
+/// so the input never has to fit into memory.
+pub fn read_distinct_window(
+    mut reader: impl std::io::Read,
+    w: usize,
+) -> std::io::Result<Option<u64>> {
+    if w == 0 {
+        return Ok(Some(0));
+    }
+
+    let mut window = DistinctWindow::new(w);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if buf[..n].iter().any(|&b| window.push(b)) {
+            return Ok(Some(window.position()));
+        }
+    }
+}
+
+/// Every position in `input` that ends `w` distinct bytes, see
+/// `distinct_windows`.
+#[derive(Debug, Clone)]
+pub struct DistinctWindows<'a> {
+    input: std::slice::Iter<'a, u8>,
+    window: DistinctWindow,
+    overlapping: bool,
+    // the first position a marker may end at
+    next_allowed: u64,
+    // an empty window is found before the first byte already
+    at_start: bool,
+}
+
+impl DistinctWindows<'_> {
+    /// Skips markers that share bytes with the one found before them.
+    pub fn non_overlapping(mut self) -> Self {
+        self.overlapping = false;
+        self
+    }
+}
+
+impl Iterator for DistinctWindows<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if std::mem::take(&mut self.at_start) && self.window.w == 0 {
+            return Some(0);
+        }
+
+        for &b in self.input.by_ref() {
+            if self.window.push(b) && self.window.position() >= self.next_allowed {
+                let end = self.window.position();
+                if !self.overlapping {
+                    self.next_allowed = end + self.window.w as u64;
+                }
+                return Some(end as usize);
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterates over all positions right after `w` distinct bytes of `input`,
+/// in order.
+pub fn distinct_windows(input: &[u8], w: usize) -> DistinctWindows<'_> {
+    DistinctWindows {
+        input: input.iter(),
+        window: DistinctWindow::new(w),
+        overlapping: true,
+        next_allowed: 0,
+        at_start: true,
+    }
+}
+
+/// The longest stretch of `input` in which no byte repeats. Of several
+/// equally long ones the first is returned.
+pub fn longest_distinct(input: &[u8]) -> std::ops::Range<usize> {
+    let mut window = DistinctWindow::new(0);
+    let mut longest = 0..0;
+    for &b in input {
+        window.push(b);
+        if window.run() as usize > longest.len() {
+            let end = window.position() as usize;
+            longest = end - window.run() as usize..end;
+        }
+    }
+    longest
+}
+
+/// The number of different bytes among the last `w` at each position of
+/// `input`: entry `i` counts the window ending with byte `i`, which is
+/// shorter near the start.
+pub fn distinct_profile(input: &[u8], w: usize) -> Vec<usize> {
+    let mut counts = [0usize; 256];
+    let mut distinct = 0;
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            counts[b as usize] += 1;
+            if counts[b as usize] == 1 {
+                distinct += 1;
+            }
+            if i >= w {
+                let old = input[i - w] as usize;
+                counts[old] -= 1;
+                if counts[old] == 0 {
+                    distinct -= 1;
+                }
+            }
+            distinct
+        })
+        .collect()
+}
+
+/// A xorshift random number generator: fast, reproducible from its seed and
+/// good enough for test and benchmark data, but nothing more.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// A seed of 0 would only ever give 0, it is taken as 1.
+    pub fn new(seed: u64) -> Self {
+        XorShift(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number from `0` up to but not including `bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
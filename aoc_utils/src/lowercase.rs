@@ -0,0 +1,217 @@
+//! The fast path of `find_distinct_window` for input made of `a` to `z`.
+//!
+//! Every letter is a bit, and `prefixes` holds the XOR of all letters up to
+//! each byte. The letters of a window are the XOR of the prefixes at its two
+//! ends: duplicates cancel out, so the window is distinct exactly if `w` bits
+//! are set. The input is searched in blocks, which leaves the popcounts of a
+//! block independent of each other and easy to do side by side.
+
+/// How much input is checked and searched at once.
+pub const BLOCK: usize = 64;
+
+/// Counts the set bits of the windows in a block and finds the first one
+/// with `w` of them.
+#[derive(Debug, Clone, Copy)]
+enum Kernel {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Popcnt,
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    Avx2,
+}
+
+impl Kernel {
+    fn detect() -> Self {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            return Kernel::Avx2;
+        }
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("popcnt") {
+            return Kernel::Popcnt;
+        }
+        Kernel::Scalar
+    }
+}
+
+/// The search state between blocks.
+#[derive(Debug, Clone)]
+pub struct Search {
+    w: usize,
+    kernel: Kernel,
+    // the prefixes of the previous block followed by the ones of the
+    // current block, before the first block they are all empty
+    prefixes: [u32; 2 * BLOCK],
+}
+
+impl Search {
+    /// Searches for `w` distinct letters, `w` has to be between 1 and 26.
+    pub fn new(w: usize) -> Self {
+        assert!((1..=26).contains(&w));
+        Search {
+            w,
+            kernel: Kernel::detect(),
+            prefixes: [0; 2 * BLOCK],
+        }
+    }
+
+    /// Takes the next block of at most `BLOCK` lowercase letters, returns
+    /// the index of the first letter in it that ends a distinct window.
+    pub fn block(&mut self, block: &[u8]) -> Option<usize> {
+        debug_assert!(block.len() <= BLOCK && is_lowercase(block));
+
+        self.prefixes.copy_within(BLOCK.., 0);
+        let mut prefix = self.prefixes[BLOCK - 1];
+        for (p, &b) in self.prefixes[BLOCK..].iter_mut().zip(block) {
+            prefix ^= 1 << (b - b'a');
+            *p = prefix;
+        }
+
+        let (prefixes, w, len) = (&self.prefixes, self.w, block.len());
+        match self.kernel {
+            Kernel::Scalar => first_window(prefixes, w, len),
+            // SAFETY: the kernels are only picked if the CPU has the features
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Popcnt => unsafe { first_window_popcnt(prefixes, w, len) },
+            #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+            Kernel::Avx2 => unsafe { first_window_avx2(prefixes, w, len) },
+        }
+    }
+}
+
+#[inline(always)]
+fn first_window(prefixes: &[u32; 2 * BLOCK], w: usize, len: usize) -> Option<usize> {
+    (0..len).find(|&i| (prefixes[BLOCK + i] ^ prefixes[BLOCK + i - w]).count_ones() as usize == w)
+}
+
+/// `first_window` with a single instruction per popcount instead of a
+/// handful of bit tricks.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+unsafe fn first_window_popcnt(prefixes: &[u32; 2 * BLOCK], w: usize, len: usize) -> Option<usize> {
+    first_window(prefixes, w, len)
+}
+
+/// Counts the bits of eight windows at once: every nibble is looked up in a
+/// table of bit counts, and a multiplication adds up the four bytes of each
+/// window.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn first_window_avx2(prefixes: &[u32; 2 * BLOCK], w: usize, len: usize) -> Option<usize> {
+    use std::arch::x86_64::*;
+
+    let table = _mm256_setr_epi8(
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, //
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+    );
+    let nibble = _mm256_set1_epi8(0x0f);
+    let target = _mm256_set1_epi32(w as i32);
+
+    let mut found = 0u64;
+    for i in (0..len).step_by(8) {
+        // the last loads may read past the block into the stale end of the
+        // array, those windows are masked off below
+        let end = _mm256_loadu_si256(prefixes.as_ptr().add(BLOCK + i) as *const __m256i);
+        let start = _mm256_loadu_si256(prefixes.as_ptr().add(BLOCK + i - w) as *const __m256i);
+        let letters = _mm256_xor_si256(end, start);
+
+        let low = _mm256_shuffle_epi8(table, _mm256_and_si256(letters, nibble));
+        let high = _mm256_shuffle_epi8(
+            table,
+            _mm256_and_si256(_mm256_srli_epi16(letters, 4), nibble),
+        );
+        let bytes = _mm256_add_epi8(low, high);
+        let counts = _mm256_srli_epi32(
+            _mm256_mullo_epi32(bytes, _mm256_set1_epi32(0x0101_0101)),
+            24,
+        );
+
+        let hits = _mm256_cmpeq_epi32(counts, target);
+        found |= (_mm256_movemask_ps(_mm256_castsi256_ps(hits)) as u64) << i;
+    }
+
+    if len < BLOCK {
+        found &= (1 << len) - 1;
+    }
+    (found != 0).then(|| found.trailing_zeros() as usize)
+}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub fn is_lowercase(input: &[u8]) -> bool {
+    // without an early exit the compiler is free to check several at once
+    input
+        .iter()
+        .fold(true, |all, b| all & b.is_ascii_lowercase())
+}
+
+/// Checks 16 bytes at a time with SSE2, which every x86_64 has.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub fn is_lowercase(input: &[u8]) -> bool {
+    use std::arch::x86_64::*;
+
+    let mut chunks = input.chunks_exact(16);
+    for chunk in chunks.by_ref() {
+        // SAFETY: SSE2 is part of x86_64 and the load is unaligned and
+        // within the 16 bytes of the chunk
+        let all = unsafe {
+            let v = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            // b - 'a' < 26 as unsigned bytes, SSE2 only compares signed ones
+            let offset = _mm_xor_si128(
+                _mm_sub_epi8(v, _mm_set1_epi8(b'a' as i8)),
+                _mm_set1_epi8(i8::MIN),
+            );
+            let below = _mm_cmplt_epi8(offset, _mm_set1_epi8(26 ^ i8::MIN));
+            _mm_movemask_epi8(below) == 0xffff
+        };
+        if !all {
+            return false;
+        }
+    }
+    chunks.remainder().iter().all(u8::is_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernels_agree() {
+        let mut rng = crate::XorShift::new(7);
+        let mut prefixes = [0u32; 2 * BLOCK];
+        for _ in 0..200 {
+            for p in prefixes.iter_mut() {
+                let state = rng.next_u64();
+                // few letters per prefix, so windows of any size show up
+                *p = (state as u32) & (state >> 32) as u32 & 0x3ff_ffff;
+            }
+
+            for w in 1..=26 {
+                for len in [1, 7, 8, 9, 63, 64] {
+                    let expected = first_window(&prefixes, w, len);
+                    #[cfg(target_arch = "x86_64")]
+                    if is_x86_feature_detected!("popcnt") {
+                        assert_eq!(unsafe { first_window_popcnt(&prefixes, w, len) }, expected);
+                    }
+                    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+                    if is_x86_feature_detected!("avx2") {
+                        assert_eq!(unsafe { first_window_avx2(&prefixes, w, len) }, expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_lowercase() {
+        let letters = b"abcdefghijklmnopqrstuvwxyz".repeat(3);
+        assert!(is_lowercase(&letters));
+        assert!(is_lowercase(b""));
+        for at in [0, 15, 16, 40, letters.len() - 1] {
+            for c in [b'A', b'`', b'{', b'0', 0xe9] {
+                let mut other = letters.clone();
+                other[at] = c;
+                assert!(!is_lowercase(&other), "{} {}", at, c);
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{Assignment, Range};
+
+/// A half-open interval of sections `[start, end)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Interval {
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    #[inline]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// The range ends at section `u64::MAX`, which has no end after it.
+#[derive(Debug, PartialEq)]
+pub struct SectionOutOfRange;
+
+impl TryFrom<&Range> for Interval {
+    type Error = SectionOutOfRange;
+
+    // `2-4` in the input covers sections 2, 3 and 4
+    fn try_from(r: &Range) -> Result<Self, Self::Error> {
+        Ok(Interval {
+            start: r.range.start,
+            end: r.range.end.checked_add(1).ok_or(SectionOutOfRange)?,
+        })
+    }
+}
+
+/// The interval of a range of a parsed assignment, which `parse_line` made
+/// sure has one.
+fn interval(r: &Range) -> Interval {
+    Interval::try_from(r).expect("assignments end before the last section")
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end - 1)
+    }
+}
+
+/// A set of sections stored as sorted, disjoint and non-adjacent intervals.
+#[derive(Debug, Default, PartialEq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn iter(&self) -> std::slice::Iter<'_, Interval> {
+        self.intervals.iter()
+    }
+
+    /// Number of sections in the set.
+    pub fn sections(&self) -> u64 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    /// The holes between the first and the last interval of the set.
+    pub fn gaps(&self) -> IntervalSet {
+        let intervals = self
+            .intervals
+            .windows(2)
+            .map(|w| Interval {
+                start: w[0].end,
+                end: w[1].start,
+            })
+            .collect();
+        IntervalSet { intervals }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        let mut sorted: Vec<Interval> = iter.into_iter().filter(|i| !i.is_empty()).collect();
+        sorted.sort();
+
+        let mut intervals: Vec<Interval> = Vec::with_capacity(sorted.len());
+        for i in sorted {
+            match intervals.last_mut() {
+                Some(last) if i.start <= last.end => last.end = last.end.max(i.end),
+                _ => intervals.push(i),
+            }
+        }
+
+        IntervalSet { intervals }
+    }
+}
+
+/// How many ranges cover each section, as a list of constant-depth segments
+/// produced by a sweep over all range boundaries.
+#[derive(Debug)]
+pub struct Coverage {
+    segments: Vec<(Interval, usize)>,
+}
+
+impl Coverage {
+    pub fn new<'a>(ranges: impl IntoIterator<Item = &'a Range>) -> Self {
+        let mut events: Vec<(u64, isize)> = ranges
+            .into_iter()
+            .map(interval)
+            .flat_map(|i| [(i.start, 1), (i.end, -1)])
+            .collect();
+        events.sort();
+
+        let mut segments = vec![];
+        let mut depth = 0isize;
+        let mut last = None;
+        for (pos, delta) in events {
+            if let Some(start) = last {
+                if depth > 0 && pos > start {
+                    segments.push((Interval { start, end: pos }, depth as usize));
+                }
+            }
+            depth += delta;
+            last = Some(pos);
+        }
+
+        Coverage { segments }
+    }
+
+    /// Sections that are covered by at least `k` ranges.
+    pub fn at_least(&self, k: usize) -> IntervalSet {
+        self.segments
+            .iter()
+            .filter(|(_, depth)| *depth >= k)
+            .map(|(i, _)| *i)
+            .collect()
+    }
+
+    /// Sections that nobody covers, between the lowest and highest covered
+    /// section.
+    pub fn uncovered(&self) -> IntervalSet {
+        self.at_least(1).gaps()
+    }
+}
+
+/// A static interval tree over the ranges of all assignments. The intervals
+/// are sorted by start and laid out as an implicit balanced binary tree in
+/// which every node knows the largest end in its subtree.
+#[derive(Debug)]
+pub struct IntervalTree {
+    // (interval, index of the assignment it came from)
+    nodes: Vec<(Interval, usize)>,
+    max_end: Vec<u64>,
+}
+
+impl IntervalTree {
+    pub fn new(assignments: &[Assignment]) -> Self {
+        let mut nodes: Vec<(Interval, usize)> = assignments
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, a)| a.ranges().map(move |r| (interval(r), idx)))
+            .collect();
+        nodes.sort();
+
+        let mut max_end = vec![0; nodes.len()];
+        Self::build(&nodes, &mut max_end, 0, nodes.len());
+
+        IntervalTree { nodes, max_end }
+    }
+
+    fn build(nodes: &[(Interval, usize)], max_end: &mut [u64], lo: usize, hi: usize) -> u64 {
+        if lo >= hi {
+            return 0;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let left = Self::build(nodes, max_end, lo, mid);
+        let right = Self::build(nodes, max_end, mid + 1, hi);
+        max_end[mid] = nodes[mid].0.end.max(left).max(right);
+        max_end[mid]
+    }
+
+    /// Indices of all assignments with at least one range overlapping `query`.
+    pub fn overlapping(&self, query: Interval) -> Vec<usize> {
+        let mut found = vec![];
+        let mut stack = vec![(0, self.nodes.len())];
+
+        while let Some((lo, hi)) = stack.pop() {
+            if lo >= hi {
+                continue;
+            }
+            let mid = lo + (hi - lo) / 2;
+            // nothing in this subtree reaches into the query
+            if self.max_end[mid] <= query.start {
+                continue;
+            }
+
+            let (interval, idx) = self.nodes[mid];
+            if interval.overlaps(&query) {
+                found.push(idx);
+            }
+
+            stack.push((lo, mid));
+            // everything right of mid starts at or after this node
+            if interval.start < query.end {
+                stack.push((mid + 1, hi));
+            }
+        }
+
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8"#;
+
+    fn interval(start: u64, end: u64) -> Interval {
+        Interval {
+            start,
+            end: end + 1,
+        }
+    }
+
+    #[test]
+    fn test_interval_set_merges() {
+        let set: IntervalSet = [
+            interval(5, 7),
+            interval(1, 2),
+            interval(3, 4),
+            interval(10, 10),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            vec![interval(1, 7), interval(10, 10)]
+        );
+        assert_eq!(set.sections(), 8);
+        assert_eq!(
+            set.gaps().iter().copied().collect::<Vec<_>>(),
+            vec![interval(8, 9)]
+        );
+    }
+
+    #[test]
+    fn test_last_section() {
+        let assignments = crate::parse_input("0-18446744073709551614,5-6").unwrap();
+        let ranges = || assignments.iter().flat_map(Assignment::ranges);
+        assert_eq!(
+            Interval::try_from(ranges().next().unwrap()).unwrap().end,
+            u64::MAX
+        );
+        assert_eq!(Coverage::new(ranges()).at_least(2).sections(), 2);
+
+        let (_, last) = Range::parse("18446744073709551615-18446744073709551615").unwrap();
+        assert_eq!(Interval::try_from(&last), Err(SectionOutOfRange));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let assignments = crate::parse_input(EXAMPLE).unwrap();
+        let coverage = Coverage::new(assignments.iter().flat_map(Assignment::ranges));
+
+        assert_eq!(
+            coverage.at_least(1).iter().copied().collect::<Vec<_>>(),
+            vec![interval(2, 9)]
+        );
+        assert_eq!(
+            coverage.at_least(7).iter().copied().collect::<Vec<_>>(),
+            vec![interval(4, 6)]
+        );
+        assert_eq!(
+            coverage.at_least(8).iter().copied().collect::<Vec<_>>(),
+            vec![interval(6, 6)]
+        );
+        assert_eq!(coverage.at_least(9), IntervalSet::default());
+        assert_eq!(coverage.uncovered(), IntervalSet::default());
+
+        let assignments = crate::parse_input("1-2,8-9\n4-4,8-8").unwrap();
+        let coverage = Coverage::new(assignments.iter().flat_map(Assignment::ranges));
+        assert_eq!(
+            coverage.uncovered().iter().copied().collect::<Vec<_>>(),
+            vec![interval(3, 3), interval(5, 7)]
+        );
+    }
+
+    #[test]
+    fn test_interval_tree() {
+        let assignments = crate::parse_input(EXAMPLE).unwrap();
+        let tree = IntervalTree::new(&assignments);
+
+        assert_eq!(tree.overlapping(interval(9, 9)), vec![2]);
+        assert_eq!(tree.overlapping(interval(1, 1)), Vec::<usize>::new());
+        assert_eq!(tree.overlapping(interval(4, 4)), vec![0, 1, 3, 4, 5]);
+        assert_eq!(tree.overlapping(interval(0, 100)), vec![0, 1, 2, 3, 4, 5]);
+    }
+}
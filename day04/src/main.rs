@@ -1,13 +1,32 @@
-use nom::character::complete::{char, digit1, newline};
-use nom::combinator::map_res;
-use nom::sequence::tuple;
+mod intervals;
+
+use intervals::{Coverage, Interval, IntervalSet, IntervalTree};
+use nom::character::complete::{char, digit1, space0};
+use nom::combinator::{all_consuming, map_res};
+use nom::sequence::{delimited, tuple};
 use nom::Parser;
 use std::str::FromStr;
 
-#[derive(Debug)]
+/// Problems with a line of the input, `line` is 1-based.
+#[derive(Debug, PartialEq)]
 pub enum Error {
-    ParsingRange,
-    NotEnoughPartsInRange,
+    ParsingRange {
+        line: usize,
+    },
+    NotEnoughPartsInRange {
+        line: usize,
+    },
+    ReversedRange {
+        line: usize,
+    },
+    /// a line needs at least two ranges to compare
+    NotEnoughRanges {
+        line: usize,
+    },
+    /// a range reaches section `u64::MAX`, which intervals can't hold
+    SectionOutOfRange {
+        line: usize,
+    },
 }
 
 struct Range {
@@ -16,12 +35,17 @@ struct Range {
 
 impl Range {
     fn parse(input: &str) -> nom::IResult<&str, Range> {
-        let digit = |input| map_res(digit1, u64::from_str)(input);
+        let digit = |input| delimited(space0, map_res(digit1, u64::from_str), space0)(input);
         tuple((digit, char('-'), digit))
             .map(|(fst, _, lst)| Range { range: fst..lst })
             .parse(input)
     }
 
+    #[inline]
+    fn is_reversed(&self) -> bool {
+        self.range.start > self.range.end
+    }
+
     #[inline]
     fn fully_contains(&self, other: &Self) -> bool {
         self.range.start <= other.range.start && self.range.end >= other.range.end
@@ -31,37 +55,113 @@ impl Range {
     fn overlaps(&self, other: &Self) -> bool {
         self.range.contains(&other.range.start)
             || self.range.contains(&other.range.end)
-            || self.fully_contains(&other)
+            || self.fully_contains(other)
     }
 }
 
 struct Assignment {
-    range1: Range,
-    range2: Range,
+    ranges: Vec<Range>,
+    /// the line of the input it is on, 1-based, 0 if it didn't come from one
+    line: usize,
 }
 
 impl Assignment {
     fn parse(input: &str) -> nom::IResult<&str, Self> {
-        tuple((Range::parse, char(','), Range::parse))
-            .map(|(range1, _, range2)| Assignment { range1, range2 })
+        nom::multi::separated_list1(char(','), Range::parse)
+            .map(|ranges| Assignment { ranges, line: 0 })
             .parse(input)
     }
 
+    /// Parses a whole line, `line` is its 1-based number in the input.
+    fn parse_line(input: &str, line: usize) -> Result<Self, Error> {
+        let assignment = match all_consuming(Assignment::parse).parse(input) {
+            Ok((_, assignment)) => assignment,
+            // a lone number parses fine on its own, it just lacks the `-<to>` part
+            Err(_)
+                if input.split(',').any(|part| {
+                    let part = part.trim();
+                    !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())
+                }) =>
+            {
+                return Err(Error::NotEnoughPartsInRange { line })
+            }
+            Err(_) => return Err(Error::ParsingRange { line }),
+        };
+
+        if assignment.ranges.len() < 2 {
+            return Err(Error::NotEnoughRanges { line });
+        }
+        if assignment.ranges().any(Range::is_reversed) {
+            return Err(Error::ReversedRange { line });
+        }
+        if assignment.ranges().any(|r| Interval::try_from(r).is_err()) {
+            return Err(Error::SectionOutOfRange { line });
+        }
+
+        Ok(Assignment { line, ..assignment })
+    }
+
+    fn ranges(&self) -> impl Iterator<Item = &Range> {
+        self.ranges.iter()
+    }
+
+    fn pairwise(&self, f: impl Fn(&Range, &Range) -> bool) -> Vec<Vec<bool>> {
+        self.ranges
+            .iter()
+            .map(|a| self.ranges.iter().map(|b| f(a, b)).collect())
+            .collect()
+    }
+
+    /// `m[i][j]` is true when range `i` fully contains range `j`.
+    fn containment_matrix(&self) -> Vec<Vec<bool>> {
+        self.pairwise(Range::fully_contains)
+    }
+
+    /// `m[i][j]` is true when range `i` and range `j` overlap.
+    fn overlap_matrix(&self) -> Vec<Vec<bool>> {
+        self.pairwise(|a, b| a.overlaps(b) || b.overlaps(a))
+    }
+
+    /// Some range fully contains some other range on this line.
     fn fully_contains(&self) -> bool {
-        self.range1.fully_contains(&self.range2) || self.range2.fully_contains(&self.range1)
+        let m = self.containment_matrix();
+        (0..m.len()).any(|i| (0..m.len()).any(|j| i != j && m[i][j]))
     }
 
+    /// Some pair of ranges on this line overlaps.
     fn overlaps(&self) -> bool {
-	self.range1.overlaps(&self.range2) || self.range2.overlaps(&self.range1)
+        let m = self.overlap_matrix();
+        (0..m.len()).any(|i| (0..m.len()).any(|j| i != j && m[i][j]))
+    }
+
+    /// A single range fully contains every other range on this line.
+    fn contains_all(&self) -> bool {
+        self.containment_matrix()
+            .iter()
+            .any(|row| row.iter().all(|c| *c))
+    }
+
+    /// Every pair of ranges on this line overlaps.
+    fn all_overlap(&self) -> bool {
+        self.overlap_matrix()
+            .iter()
+            .enumerate()
+            .all(|(i, row)| row.iter().enumerate().all(|(j, o)| i == j || *o))
     }
 }
 
-fn parse_input(input: &str) -> nom::IResult<&str, Vec<Assignment>> {
-    nom::multi::separated_list1(newline, Assignment::parse).parse(input)
+fn parse_input(input: &str) -> Result<Vec<Assignment>, Error> {
+    // `lines` takes care of `\r\n` line endings and a trailing newline
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| Assignment::parse_line(line, n + 1))
+        .collect()
 }
 
 fn part1(input: &str) -> usize {
-    let (_, assignments) = parse_input(input).unwrap();
+    let assignments = parse_input(input).unwrap();
 
     assignments
         .into_iter()
@@ -70,18 +170,99 @@ fn part1(input: &str) -> usize {
 }
 
 fn part2(input: &str) -> usize {
-    let (_, assignments) = parse_input(input).unwrap();
+    let assignments = parse_input(input).unwrap();
+
+    assignments.into_iter().filter(Assignment::overlaps).count()
+}
+
+fn count_contains_all(input: &str) -> usize {
+    let assignments = parse_input(input).unwrap();
+
+    assignments
+        .into_iter()
+        .filter(Assignment::contains_all)
+        .count()
+}
+
+fn count_all_overlap(input: &str) -> usize {
+    let assignments = parse_input(input).unwrap();
 
     assignments
         .into_iter()
-        .filter(Assignment::overlaps)
+        .filter(Assignment::all_overlap)
         .count()
 }
 
+fn covered_by_at_least(input: &str, k: usize) -> IntervalSet {
+    let assignments = parse_input(input).unwrap();
+
+    Coverage::new(assignments.iter().flat_map(Assignment::ranges)).at_least(k)
+}
+
+fn uncovered(input: &str) -> IntervalSet {
+    let assignments = parse_input(input).unwrap();
+
+    Coverage::new(assignments.iter().flat_map(Assignment::ranges)).uncovered()
+}
+
+/// The lines of all assignments overlapping `query`.
+fn overlapping(input: &str, query: Interval) -> Vec<usize> {
+    let assignments = parse_input(input).unwrap();
+
+    IntervalTree::new(&assignments)
+        .overlapping(query)
+        .into_iter()
+        .map(|idx| assignments[idx].line)
+        .collect()
+}
+
+fn print_intervals(intervals: &IntervalSet) {
+    for i in intervals.iter() {
+        println!("{}", i);
+    }
+    println!("sections: {}", intervals.sections());
+}
+
+fn usage() -> ! {
+    eprintln!("usage: day04 [crews | covered <k> | uncovered | overlapping <from>-<to>]");
+    std::process::exit(1);
+}
 
 fn main() {
-    println!("part1: {}", part1(include_str!("input.txt")));
-    println!("part2: {}", part2(include_str!("input.txt")));
+    let input = include_str!("input.txt");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        [] => {
+            println!("part1: {}", part1(input));
+            println!("part2: {}", part2(input));
+        }
+        ["crews"] => {
+            println!("contains all: {}", count_contains_all(input));
+            println!("all overlap: {}", count_all_overlap(input));
+        }
+        ["covered", k] => {
+            let k = match k.parse() {
+                Ok(k) if k > 0 => k,
+                _ => usage(),
+            };
+            print_intervals(&covered_by_at_least(input, k));
+        }
+        ["uncovered"] => print_intervals(&uncovered(input)),
+        ["overlapping", range] => {
+            let query = match Range::parse(range) {
+                Ok(("", query)) if !query.is_reversed() => {
+                    Interval::try_from(&query).unwrap_or_else(|_| usage())
+                }
+                _ => usage(),
+            };
+            for line in overlapping(input, query) {
+                println!("{}", line);
+            }
+        }
+        _ => usage(),
+    }
 }
 
 #[cfg(test)]
@@ -108,24 +289,35 @@ mod tests {
     #[test]
     fn test_parse_assignment() {
         let (_, r) = super::Assignment::parse("1-5,3-5").unwrap();
-        assert_eq!(r.range1.range, 1..5);
-        assert_eq!(r.range2.range, 3..5);
+        assert_eq!(r.ranges[0].range, 1..5);
+        assert_eq!(r.ranges[1].range, 3..5);
+
+        let (_, r) = super::Assignment::parse("1-5,3-5,2-9,4-4").unwrap();
+        assert_eq!(r.ranges.len(), 4);
+        assert_eq!(r.ranges[2].range, 2..9);
+        assert_eq!(r.ranges[3].range, 4..4);
     }
 
     #[test]
     fn test_parse_input() {
-        let (rem, l) = super::parse_input("1-2,3-5\n4-5,3-6").unwrap();
-        assert_eq!(rem, "");
+        let l = super::parse_input("1-2,3-5\n4-5,3-6").unwrap();
         assert_eq!(l.len(), 2);
-        assert_eq!(l[0].range1.range.start, 1);
-        assert_eq!(l[0].range1.range.end, 2);
-        assert_eq!(l[0].range2.range.start, 3);
-        assert_eq!(l[0].range2.range.end, 5);
+        assert_eq!(l[0].ranges[0].range.start, 1);
+        assert_eq!(l[0].ranges[0].range.end, 2);
+        assert_eq!(l[0].ranges[1].range.start, 3);
+        assert_eq!(l[0].ranges[1].range.end, 5);
+
+        assert_eq!(l[1].ranges[0].range.start, 4);
+        assert_eq!(l[1].ranges[0].range.end, 5);
+        assert_eq!(l[1].ranges[1].range.start, 3);
+        assert_eq!(l[1].ranges[1].range.end, 6);
+    }
 
-        assert_eq!(l[1].range1.range.start, 4);
-        assert_eq!(l[1].range1.range.end, 5);
-        assert_eq!(l[1].range2.range.start, 3);
-        assert_eq!(l[1].range2.range.end, 6);
+    #[test]
+    fn test_overlapping_lines() {
+        let query = super::Interval { start: 8, end: 9 };
+        assert_eq!(super::overlapping("1-2,3-3\n\n\n7-9,8-8", query), vec![4]);
+        assert_eq!(super::overlapping("7-9,1-1\n1-2,3-3", query), vec![1]);
     }
 
     #[test]
@@ -137,9 +329,95 @@ mod tests {
         assert!(!b.fully_contains(&a));
 
         let a = super::Assignment {
-            range1: a,
-            range2: b,
+            ranges: vec![a, b],
+            line: 0,
         };
         assert!(a.fully_contains());
+        assert!(a.contains_all());
+    }
+
+    #[test]
+    fn test_crews() {
+        let (_, a) = super::Assignment::parse("1-9,2-4,6-8").unwrap();
+        assert_eq!(
+            a.containment_matrix(),
+            vec![
+                vec![true, true, true],
+                vec![false, true, false],
+                vec![false, false, true]
+            ]
+        );
+        assert!(a.fully_contains());
+        assert!(a.contains_all());
+        assert!(a.overlaps());
+        assert!(!a.all_overlap());
+
+        let (_, a) = super::Assignment::parse("1-5,3-7,4-9,2-6").unwrap();
+        assert!(!a.fully_contains());
+        assert!(!a.contains_all());
+        assert!(a.all_overlap());
+
+        assert_eq!(super::count_contains_all("1-9,2-4,6-8\n1-5,3-7,4-9"), 1);
+        assert_eq!(super::count_all_overlap("1-9,2-4,6-8\n1-5,3-7,4-9"), 1);
+    }
+
+    #[test]
+    fn test_parse_input_variations() {
+        let l = super::parse_input("1-2,3-5\r\n4-5,3-6\r\n").unwrap();
+        assert_eq!(l.len(), 2);
+        assert_eq!(l[1].ranges[1].range, 3..6);
+
+        let l = super::parse_input("1 - 2 , 3-5\n\t4-5,3 -6\n\n").unwrap();
+        assert_eq!(l.len(), 2);
+        assert_eq!(l[0].ranges[0].range, 1..2);
+        assert_eq!(l[0].ranges[1].range, 3..5);
+        assert_eq!(l[1].ranges[0].range, 4..5);
+        assert_eq!(l[1].ranges[1].range, 3..6);
+    }
+
+    #[test]
+    fn test_parse_input_errors() {
+        use super::Error;
+
+        assert_eq!(
+            super::parse_input("1-2,3-5\n8-2,3-6").err(),
+            Some(Error::ReversedRange { line: 2 })
+        );
+        assert_eq!(
+            super::parse_input("1-2,3-5\n1-5").err(),
+            Some(Error::NotEnoughRanges { line: 2 })
+        );
+        assert_eq!(
+            super::parse_input("1-2,3").err(),
+            Some(Error::NotEnoughPartsInRange { line: 1 })
+        );
+        assert_eq!(
+            super::parse_input("1-2,3-5\n\n1-2;3-5").err(),
+            Some(Error::ParsingRange { line: 3 })
+        );
+        assert_eq!(
+            super::parse_input("1-2,3-5x").err(),
+            Some(Error::ParsingRange { line: 1 })
+        );
+        for input in ["1-2,3-5,", "1-2,,3-5", "1-2,x"] {
+            assert_eq!(
+                super::parse_input(input).err(),
+                Some(Error::ParsingRange { line: 1 }),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            super::parse_input("1-2, 3 ,4-5").err(),
+            Some(Error::NotEnoughPartsInRange { line: 1 })
+        );
+        assert_eq!(
+            super::parse_input("1-2,3-5\n0-18446744073709551615,3-5").err(),
+            Some(Error::SectionOutOfRange { line: 2 })
+        );
+        assert_eq!(
+            super::parse_input("0-18446744073709551614,3-5").unwrap()[0].ranges[0].range,
+            0..u64::MAX - 1
+        );
     }
 }
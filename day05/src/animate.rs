@@ -0,0 +1,242 @@
+use crate::replay::Replay;
+use crate::{CargoBay, Crate, Move, MoveError};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Color, Stylize};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::Write;
+use std::time::Duration;
+
+const HELP: &str = "space: pause  n/→: step  b/←: back  +/-: speed  q: quit";
+
+/// Restores the terminal when the animation ends, even on errors.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter(out: &mut impl Write) -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(
+            std::io::stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Draws the bay upright like `CargoBay::render`, with the source of the
+/// upcoming move in yellow and its destination in green.
+fn frame(bay: &CargoBay, next: Option<&Move>) -> Vec<String> {
+    let color = |stack: usize| match next {
+        Some(m) if m.from == stack => Some(Color::Yellow),
+        Some(m) if m.to == stack => Some(Color::Green),
+        _ => None,
+    };
+
+    bay.drawing()
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .enumerate()
+                .map(|(i, cell)| match color(i + 1) {
+                    Some(c) if !cell.trim().is_empty() => {
+                        cell.with(c).attribute(Attribute::Bold).to_string()
+                    }
+                    _ => cell,
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// The top crate of every stack, `None` for empty ones, so stacks keep their
+/// place when one of them empties.
+fn tops(bay: &CargoBay) -> Vec<Option<Crate>> {
+    bay.stacks.iter().map(|s| s.items.last().cloned()).collect()
+}
+
+/// The top-of-stacks answer, crates that changed since `previous` are
+/// highlighted.
+fn answer(top: &[Option<Crate>], previous: &[Option<Crate>]) -> String {
+    top.iter()
+        .zip(previous)
+        .filter_map(|(c, p)| {
+            let c = c.as_ref()?;
+            Some(if Some(c) == p.as_ref() {
+                c.0.to_string()
+            } else {
+                c.0.to_string().reverse().to_string()
+            })
+        })
+        .collect()
+}
+
+fn draw(
+    out: &mut impl Write,
+    replay: &Replay,
+    previous: &[Option<Crate>],
+    delay: Duration,
+    paused: bool,
+) -> std::io::Result<()> {
+    let (_, rows) = terminal::size()?;
+    let next = replay.remaining().first();
+
+    let mut lines = vec![
+        format!(
+            "move {}/{}  delay {}ms{}",
+            replay.position(),
+            replay.len(),
+            delay.as_millis(),
+            if paused { "  [paused]" } else { "" }
+        ),
+        match next {
+            Some(m) => format!(
+                "next: move {} from {} to {}",
+                m.quantity,
+                m.from.to_string().yellow(),
+                m.to.to_string().green()
+            ),
+            None => "done".to_string(),
+        },
+        format!("top: {}", answer(&tops(replay.bay()), previous)),
+        String::new(),
+    ];
+
+    // keep the footer and the topmost crates on screen if the bay is taller
+    // than the terminal
+    let drawing = frame(replay.bay(), next);
+    let room = (rows as usize).saturating_sub(lines.len() + 2);
+    lines.extend(
+        drawing
+            .iter()
+            .skip(drawing.len().saturating_sub(room))
+            .cloned(),
+    );
+    lines.push(String::new());
+    lines.push(HELP.to_string());
+
+    queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    for (y, line) in lines.iter().enumerate() {
+        queue!(out, cursor::MoveTo(0, y as u16))?;
+        write!(out, "{}", line)?;
+    }
+    out.flush()
+}
+
+/// Animates the program of `replay` in the terminal until the user quits.
+pub fn animate(replay: &mut Replay) -> std::io::Result<Result<(), MoveError>> {
+    let mut out = std::io::stdout();
+    let _raw = RawTerminal::enter(&mut out)?;
+
+    let mut delay = Duration::from_millis(500);
+    let mut paused = false;
+    let mut previous = tops(replay.bay());
+
+    loop {
+        draw(&mut out, replay, &previous, delay, paused)?;
+        let current = tops(replay.bay());
+
+        // when paused there is nothing to do until a key is pressed
+        let timeout = if paused {
+            Duration::from_secs(3600)
+        } else {
+            delay
+        };
+        let step = if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(Ok(())),
+                    KeyCode::Char(' ') => {
+                        paused = !paused;
+                        None
+                    }
+                    KeyCode::Char('n') | KeyCode::Right => {
+                        paused = true;
+                        Some(true)
+                    }
+                    KeyCode::Char('b') | KeyCode::Left => {
+                        paused = true;
+                        Some(false)
+                    }
+                    KeyCode::Char('+') => {
+                        delay = (delay / 2).max(Duration::from_millis(1));
+                        None
+                    }
+                    KeyCode::Char('-') => {
+                        delay = (delay * 2).min(Duration::from_secs(5));
+                        None
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }
+        } else {
+            Some(true)
+        };
+
+        match step {
+            Some(true) => match replay.step_forward() {
+                Ok(true) => previous = current,
+                Ok(false) => paused = true,
+                Err(e) => return Ok(Err(e)),
+            },
+            Some(false) if replay.step_back() => previous = current,
+            Some(false) | None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_bay;
+
+    #[test]
+    fn test_frame() {
+        let (bay, moves) = example_bay();
+
+        assert_eq!(frame(&bay, None).join("\n") + "\n", bay.render());
+
+        let highlighted = frame(&bay, moves.first());
+        assert_eq!(highlighted.len(), 4);
+        // stack 1 is the destination, stack 2 the source, stack 3 is left alone
+        assert!(highlighted[2].starts_with(&"[Z]".with(Color::Green).bold().to_string()));
+        assert!(highlighted[0].contains(&"[D]".with(Color::Yellow).bold().to_string()));
+        assert!(highlighted[2].ends_with(" [P]"));
+    }
+
+    #[test]
+    fn test_answer() {
+        let (a, b, c) = (Crate::from("A"), Crate::from("B"), Crate::from("C"));
+        assert_eq!(
+            answer(
+                &[Some(a.clone()), Some(b.clone())],
+                &[Some(a.clone()), Some(b.clone())]
+            ),
+            "AB"
+        );
+        assert_eq!(
+            answer(
+                &[Some(a.clone()), Some(c.clone())],
+                &[Some(a.clone()), Some(b.clone())]
+            ),
+            format!("A{}", "C".reverse())
+        );
+
+        // stack 1 empties, the stacks after it are still the same
+        assert_eq!(
+            answer(
+                &[None, Some(b.clone()), Some(c.clone())],
+                &[Some(a), Some(b), Some(c)]
+            ),
+            "BC"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::crane::{Crane, CrateMover9000, CrateMover9001};
+use crate::events::Quiet;
+use crate::{CargoBay, Crate, Move, Stack};
+use aoc_utils::XorShift;
+use std::time::{Duration, Instant};
+
+/// The CrateMover 9000 as it used to be: one pop and push per crate.
+struct PopPush9000;
+
+impl Crane for PopPush9000 {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        for _ in 0..quantity {
+            let c = from.items.pop().expect("caller checked the crate count");
+            to.items.push(c);
+        }
+    }
+}
+
+/// The CrateMover 9001 as it used to be: pops into a temporary `Vec` and
+/// pushes that back in reverse.
+struct PopPush9001;
+
+impl Crane for PopPush9001 {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        let items = (0..quantity)
+            .map(|_| from.items.pop().expect("caller checked the crate count"))
+            .collect::<Vec<_>>();
+        for item in items.into_iter().rev() {
+            to.items.push(item);
+        }
+    }
+}
+
+/// A bay of `stacks` stacks with `height` crates each and `count` random
+/// moves of up to half a stack, generated from `seed`.
+pub fn generate(stacks: usize, height: usize, count: usize, seed: u64) -> (CargoBay, Vec<Move>) {
+    let mut rng = XorShift::new(seed);
+    let mut next = move |bound: usize| rng.below(bound as u64) as usize;
+
+    let bay = CargoBay {
+        stacks: (0..stacks)
+            .map(|s| Stack {
+                items: (0..height)
+                    .map(|i| Crate(((b'A' + ((s + i) % 26) as u8) as char).to_string()))
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    let mut heights = vec![height; stacks];
+    let moves = (0..count)
+        .map(|_| {
+            let from = next(stacks);
+            let to = (from + 1 + next(stacks - 1)) % stacks;
+            let quantity = next(heights[from] / 2 + 1);
+            heights[from] -= quantity;
+            heights[to] += quantity;
+            Move {
+                quantity,
+                from: from + 1,
+                to: to + 1,
+            }
+        })
+        .collect();
+
+    (bay, moves)
+}
+
+fn time(bay: &CargoBay, moves: &[Move], crane: &mut dyn Crane) -> (Duration, CargoBay) {
+    let mut bay = bay.clone();
+    let start = Instant::now();
+    for m in moves {
+        bay.try_apply(crane, m, &mut Quiet)
+            .expect("generated moves are valid");
+    }
+    (start.elapsed(), bay)
+}
+
+/// Times the cranes against their old pop and push versions on a generated
+/// program and prints the speedup.
+pub fn bench() {
+    let (bay, moves) = generate(9, 100_000, 2_000, 2022);
+    println!(
+        "{} stacks, {} crates, {} moves",
+        bay.stacks.len(),
+        bay.stacks.iter().map(|s| s.items.len()).sum::<usize>(),
+        moves.len()
+    );
+
+    let pairs: [(&str, &mut dyn Crane, &mut dyn Crane); 2] = [
+        ("9000", &mut PopPush9000, &mut CrateMover9000),
+        ("9001", &mut PopPush9001, &mut CrateMover9001),
+    ];
+    for (name, old, new) in pairs {
+        let (before, expected) = time(&bay, &moves, old);
+        let (after, got) = time(&bay, &moves, new);
+        assert!(got == expected, "{}: the cranes disagree", name);
+        println!(
+            "{}: {:>8.2?} pop/push, {:>8.2?} drain/extend, {:.1}x",
+            name,
+            before,
+            after,
+            before.as_secs_f64() / after.as_secs_f64()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_as_pop_push() {
+        let (bay, moves) = generate(5, 50, 200, 7);
+        assert!(moves.iter().any(|m| m.quantity > 1));
+        assert_eq!(
+            time(&bay, &moves, &mut PopPush9000).1,
+            time(&bay, &moves, &mut CrateMover9000).1
+        );
+        assert_eq!(
+            time(&bay, &moves, &mut PopPush9001).1,
+            time(&bay, &moves, &mut CrateMover9001).1
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::Stack;
+
+/// A crane model, i.e. the way a crane moves several crates between two
+/// stacks.
+pub trait Crane {
+    /// Moves the top `quantity` crates of `from` onto `to`. The caller makes
+    /// sure that `from` holds at least `quantity` crates.
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize);
+}
+
+/// Moves one crate at a time, so the moved crates end up reversed.
+#[derive(Debug, Default)]
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        to.items.extend(from.lift(quantity).rev());
+    }
+}
+
+/// Moves all crates at once, keeping their order.
+#[derive(Debug, Default)]
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        to.items.extend(from.lift(quantity));
+    }
+}
+
+/// Like the CrateMover 9001 but can lift at most `capacity` crates at once,
+/// bigger moves are split into chunks of that size.
+#[derive(Debug)]
+pub struct CappedCrane {
+    capacity: usize,
+}
+
+impl CappedCrane {
+    pub fn new(capacity: usize) -> Option<Self> {
+        (capacity > 0).then_some(CappedCrane { capacity })
+    }
+}
+
+impl Crane for CappedCrane {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        let mut remaining = quantity;
+        while remaining > 0 {
+            let chunk = remaining.min(self.capacity);
+            CrateMover9001.transfer(from, to, chunk);
+            remaining -= chunk;
+        }
+    }
+}
+
+/// Alternates between reversing a batch (like the 9000) and keeping its
+/// order (like the 9001), starting with a reversed one.
+#[derive(Debug, Default)]
+pub struct AlternatingCrane {
+    keep_order: bool,
+}
+
+impl Crane for AlternatingCrane {
+    fn transfer(&mut self, from: &mut Stack, to: &mut Stack, quantity: usize) {
+        if self.keep_order {
+            CrateMover9001.transfer(from, to, quantity);
+        } else {
+            CrateMover9000.transfer(from, to, quantity);
+        }
+        self.keep_order = !self.keep_order;
+    }
+}
+
+/// Looks up a crane by the name used on the command line: `9000`, `9001`,
+/// `capped:<capacity>` or `alternating`.
+pub fn by_name(name: &str) -> Option<Box<dyn Crane>> {
+    match name {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        "alternating" => Some(Box::<AlternatingCrane>::default()),
+        _ => {
+            let capacity = name.strip_prefix("capped:")?.parse().ok()?;
+            Some(Box::new(CappedCrane::new(capacity)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Crate;
+
+    fn stack(s: &str) -> Stack {
+        Stack {
+            items: s.chars().map(|c| Crate(c.to_string())).collect(),
+        }
+    }
+
+    fn transfer(crane: &mut dyn Crane, quantity: usize) -> Stack {
+        let mut from = stack("ABCDE");
+        let mut to = stack("Z");
+        crane.transfer(&mut from, &mut to, quantity);
+        assert_eq!(from.items.len(), 5 - quantity);
+        to
+    }
+
+    #[test]
+    fn test_cranes() {
+        assert_eq!(transfer(&mut CrateMover9000, 3), stack("ZEDC"));
+        assert_eq!(transfer(&mut CrateMover9001, 3), stack("ZCDE"));
+        assert_eq!(
+            transfer(&mut CappedCrane::new(2).unwrap(), 5),
+            stack("ZDEBCA")
+        );
+
+        let mut alternating = AlternatingCrane::default();
+        assert_eq!(transfer(&mut alternating, 3), stack("ZEDC"));
+        assert_eq!(transfer(&mut alternating, 3), stack("ZCDE"));
+        assert_eq!(transfer(&mut alternating, 3), stack("ZEDC"));
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert!(by_name("9000").is_some());
+        assert!(by_name("9001").is_some());
+        assert!(by_name("alternating").is_some());
+        assert!(by_name("capped:3").is_some());
+        assert!(by_name("capped:0").is_none());
+        assert!(by_name("capped:").is_none());
+        assert!(by_name("9002").is_none());
+    }
+}
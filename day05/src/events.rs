@@ -0,0 +1,148 @@
+use crate::{Crate, Move};
+use std::io::Write;
+
+/// Things that happen to a `CargoBay` while a move is executed. Stacks are
+/// numbered from 1, like in the moves.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    MoveStarted(Move),
+    CrateLifted { stack: usize, item: Crate },
+    CratePlaced { stack: usize, item: Crate },
+}
+
+/// Receives the events of a bay.
+pub trait EventSink {
+    fn event(&mut self, event: Event);
+
+    /// Whether the sink looks at events at all. Sinks that don't spare the
+    /// bay from cloning every moved crate into an event.
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+/// Drops all events.
+#[derive(Debug, Default)]
+pub struct Quiet;
+
+impl EventSink for Quiet {
+    fn event(&mut self, _: Event) {}
+
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+/// Records all events, mostly useful for tests.
+impl EventSink for Vec<Event> {
+    fn event(&mut self, event: Event) {
+        self.push(event);
+    }
+}
+
+/// Writes a human readable trace, one line per event.
+#[derive(Debug)]
+pub struct Trace<W: Write>(pub W);
+
+impl<W: Write> EventSink for Trace<W> {
+    fn event(&mut self, event: Event) {
+        let _ = match event {
+            Event::MoveStarted(m) => {
+                writeln!(self.0, "move {} from {} to {}", m.quantity, m.from, m.to)
+            }
+            Event::CrateLifted { stack, item } => {
+                writeln!(self.0, "  lift  [{}] from {}", item.0, stack)
+            }
+            Event::CratePlaced { stack, item } => {
+                writeln!(self.0, "  place [{}] on {}", item.0, stack)
+            }
+        };
+    }
+}
+
+/// Writes one JSON object per event and line.
+#[derive(Debug)]
+pub struct JsonLines<W: Write>(pub W);
+
+impl<W: Write> JsonLines<W> {
+    fn item(c: Crate) -> String {
+        c.0.chars()
+            .map(|c| match c {
+                '"' => "\\\"".to_string(),
+                '\\' => "\\\\".to_string(),
+                c if c.is_control() => format!("\\u{:04x}", c as u32),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl<W: Write> EventSink for JsonLines<W> {
+    fn event(&mut self, event: Event) {
+        let _ = match event {
+            Event::MoveStarted(m) => writeln!(
+                self.0,
+                r#"{{"event":"move_started","quantity":{},"from":{},"to":{}}}"#,
+                m.quantity, m.from, m.to
+            ),
+            Event::CrateLifted { stack, item } => writeln!(
+                self.0,
+                r#"{{"event":"crate_lifted","stack":{},"crate":"{}"}}"#,
+                stack,
+                Self::item(item)
+            ),
+            Event::CratePlaced { stack, item } => writeln!(
+                self.0,
+                r#"{{"event":"crate_placed","stack":{},"crate":"{}"}}"#,
+                stack,
+                Self::item(item)
+            ),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> [Event; 3] {
+        [
+            Event::MoveStarted(Move {
+                quantity: 1,
+                from: 2,
+                to: 1,
+            }),
+            Event::CrateLifted {
+                stack: 2,
+                item: Crate::from("D"),
+            },
+            Event::CratePlaced {
+                stack: 1,
+                item: Crate::from("\""),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut sink = Trace(vec![]);
+        events().into_iter().for_each(|e| sink.event(e));
+        assert_eq!(
+            String::from_utf8(sink.0).unwrap(),
+            "move 1 from 2 to 1\n  lift  [D] from 2\n  place [\"] on 1\n"
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut sink = JsonLines(vec![]);
+        events().into_iter().for_each(|e| sink.event(e));
+        assert_eq!(
+            String::from_utf8(sink.0).unwrap(),
+            r#"{"event":"move_started","quantity":1,"from":2,"to":1}
+{"event":"crate_lifted","stack":2,"crate":"D"}
+{"event":"crate_placed","stack":1,"crate":"\""}
+"#
+        );
+    }
+}
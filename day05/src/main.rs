@@ -1,45 +1,202 @@
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::combinator::map_res;
-use nom::sequence::tuple;
-use nom::Parser;
+mod animate;
+mod bench;
+mod crane;
+mod events;
+mod optimize;
+mod replay;
+mod reverse;
+
+use crane::{Crane, CrateMover9000, CrateMover9001};
+use events::{Event, EventSink, JsonLines, Quiet, Trace};
+use nom::bytes::complete::{is_not, tag};
+use nom::character::complete::space0;
+use nom::combinator::{consumed, map_res};
+use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::{Offset, Parser};
+use replay::Replay;
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-struct Crate(char);
+/// A crate and its label, usually a single letter.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Crate(String);
 
-#[derive(Debug, PartialEq, Clone)]
+impl From<&str> for Crate {
+    fn from(label: &str) -> Self {
+        Crate(label.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 struct Stack {
     items: Vec<Crate>,
 }
 
-#[derive(Debug, PartialEq)]
+impl Stack {
+    /// Takes the top `quantity` crates off the stack, bottom to top. The
+    /// crates are moved out in place, so handing them to another stack with
+    /// `extend` doesn't allocate beyond the destination growing.
+    fn lift(&mut self, quantity: usize) -> std::vec::Drain<'_, Crate> {
+        let start = self.items.len() - quantity;
+        self.items.drain(start..)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 struct CargoBay {
     stacks: Vec<Stack>,
 }
 
+#[derive(Debug, PartialEq)]
+enum MoveError {
+    /// `from` or `to` doesn't name one of the stacks
+    InvalidStack(usize),
+    /// the source stack holds fewer crates than the move asks for
+    InsufficientCrates {
+        stack: usize,
+        available: usize,
+        requested: usize,
+    },
+    /// source and destination are the same stack
+    SameStack(usize),
+}
+
 impl CargoBay {
-    fn execute(&mut self, m: Move) {
-        for _ in 0..(m.quantity) {
-            match self.stacks[m.from - 1].items.pop() {
-                Some(c) => {
-                    println!("{:?} {:?}", m, c);
-                    self.stacks[m.to - 1].items.push(c);
-                }
-                None => panic!("Running out of crates!"),
+    /// Checks that `m` can be executed on the bay as it is right now.
+    fn validate(&self, m: &Move) -> Result<(), MoveError> {
+        for stack in [m.from, m.to] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(MoveError::InvalidStack(stack));
             }
         }
+
+        if m.from == m.to {
+            return Err(MoveError::SameStack(m.from));
+        }
+
+        let available = self.stacks[m.from - 1].items.len();
+        if available < m.quantity {
+            return Err(MoveError::InsufficientCrates {
+                stack: m.from,
+                available,
+                requested: m.quantity,
+            });
+        }
+
+        Ok(())
     }
 
-    fn execute_bulk(&mut self, m: Move) {
-        let src = &mut self.stacks[m.from - 1].items;
-        let items = (0..(m.quantity))
-            .filter_map(|_| src.pop())
-            .collect::<Vec<_>>();
-        println!("{:?} {:?}", m, items);
-        for item in items.iter().rev() {
-            self.stacks[m.to - 1].items.push(*item);
+    /// Executes `m` with the given crane and reports what happens to
+    /// `events`. The bay is left untouched and no events are emitted if the
+    /// move can't be executed.
+    fn try_apply(
+        &mut self,
+        crane: &mut dyn Crane,
+        m: &Move,
+        events: &mut dyn EventSink,
+    ) -> Result<(), MoveError> {
+        self.validate(m)?;
+
+        let (from, to) = self.two_stacks(m.from, m.to);
+        if !events.enabled() {
+            crane.transfer(from, to, m.quantity);
+            return Ok(());
+        }
+
+        events.event(Event::MoveStarted(*m));
+
+        // the crates leave the source stack from the top down ...
+        for item in from.items.iter().rev().take(m.quantity) {
+            events.event(Event::CrateLifted {
+                stack: m.from,
+                item: item.clone(),
+            });
+        }
+
+        crane.transfer(from, to, m.quantity);
+
+        // ... and whatever order the crane put them in, they are now the
+        // topmost crates of the destination
+        for item in &to.items[to.items.len() - m.quantity..] {
+            events.event(Event::CratePlaced {
+                stack: m.to,
+                item: item.clone(),
+            });
         }
+
+        Ok(())
+    }
+
+    /// Borrows two distinct stacks, addressed by their 1-based number.
+    fn two_stacks(&mut self, a: usize, b: usize) -> (&mut Stack, &mut Stack) {
+        assert_ne!(a, b);
+        if a < b {
+            let (lo, hi) = self.stacks.split_at_mut(b - 1);
+            (&mut lo[a - 1], &mut hi[0])
+        } else {
+            let (lo, hi) = self.stacks.split_at_mut(a - 1);
+            (&mut hi[0], &mut lo[b - 1])
+        }
+    }
+
+    /// The width of each stack in the drawing: enough for its widest crate
+    /// and its number.
+    fn column_widths(&self) -> Vec<usize> {
+        self.stacks
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                s.items
+                    .iter()
+                    .map(|c| c.0.chars().count())
+                    .chain([(i + 1).to_string().len()])
+                    .max()
+                    .unwrap_or(1)
+                    + 2
+            })
+            .collect()
+    }
+
+    /// The cells of the drawing, row by row from the top with the footer
+    /// last, each padded to the width of its column.
+    fn drawing(&self) -> Vec<Vec<String>> {
+        let height = self.stacks.iter().map(|s| s.items.len()).max().unwrap_or(0);
+        let widths = self.column_widths();
+
+        let mut rows: Vec<Vec<String>> = (0..height)
+            .rev()
+            .map(|level| {
+                self.stacks
+                    .iter()
+                    .zip(&widths)
+                    .map(|(s, width)| match s.items.get(level) {
+                        Some(c) => format!("{:<width$}", format!("[{}]", c.0)),
+                        None => " ".repeat(*width),
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.push(
+            widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| {
+                    let n = (i + 1).to_string();
+                    let pad = width - n.len();
+                    format!("{}{}{}", " ".repeat(pad / 2), n, " ".repeat(pad - pad / 2))
+                })
+                .collect(),
+        );
+
+        rows
+    }
+
+    /// Draws the bay the way the puzzle input does: the stacks standing
+    /// upright, followed by the numbered footer.
+    fn render(&self) -> String {
+        self.drawing()
+            .iter()
+            .map(|row| row.join(" ") + "\n")
+            .collect()
     }
 
     fn top_of_stacks(&self) -> Vec<Crate> {
@@ -48,25 +205,39 @@ impl CargoBay {
             .filter_map(|stack| stack.items.last().cloned())
             .collect()
     }
+}
 
-    fn print(&self) {
+impl std::fmt::Display for CargoBay {
+    /// One stack per line, bottom crate first.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for (i, stack) in self.stacks.iter().enumerate() {
-            let crates = stack
-                .items
-                .iter()
-                .fold(String::new(), |s, c| format!("{} [{}]", s, c.0));
-            println!("{} {}", i + 1, crates);
+            write!(f, "{}", i + 1)?;
+            for c in &stack.items {
+                write!(f, " [{}]", c.0)?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 struct Move {
     from: usize,
     to: usize,
     quantity: usize,
 }
 
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity, self.from, self.to
+        )
+    }
+}
+
 impl Move {
     fn parse(input: &str) -> nom::IResult<&str, Move> {
         let digit = |input| map_res(nom::character::complete::digit1, usize::from_str).parse(input);
@@ -88,35 +259,59 @@ struct Moves {
     moves: Vec<Move>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 enum LineItem {
     Empty,
     Crate(Crate),
 }
 
 impl LineItem {
+    /// Parses a single bracketed crate, the label may be longer than one
+    /// character.
     fn parse(input: &str) -> nom::IResult<&str, LineItem> {
-        let item =
-            tuple((tag("["), nom::character::complete::anychar, tag("]"))).map(|(_, c, _)| c);
-        alt((
-            tag("   ").map(|_| LineItem::Empty),
-            item.map(|c| LineItem::Crate(Crate(c))),
-        ))
-        .parse(input)
+        delimited(tag("["), is_not("[] \n"), tag("]"))
+            .map(|label: &str| LineItem::Crate(Crate::from(label)))
+            .parse(input)
     }
 }
 
-fn parse_line(input: &str) -> nom::IResult<&str, Vec<LineItem>> {
-    nom::multi::separated_list1(tag(" "), LineItem::parse).parse(input)
+/// The character columns something occupies on a line of the drawing.
+type Columns = std::ops::Range<usize>;
+
+/// Finds `item`s separated by spaces on a line, along with the columns
+/// they occupy.
+fn positioned<'a, O>(
+    line: &'a str,
+    item: impl Parser<&'a str, O, nom::error::Error<&'a str>>,
+) -> nom::IResult<&'a str, Vec<(Columns, O)>> {
+    terminated(nom::multi::many0(preceded(space0, consumed(item))), space0)
+        .map(|items| {
+            items
+                .into_iter()
+                .map(|(raw, item)| {
+                    let start = line.offset(raw);
+                    (start..start + raw.len(), item)
+                })
+                .collect()
+        })
+        .parse(line)
+}
+
+/// Parses a line of crates, empty spots are just whitespace.
+fn parse_line(input: &str) -> nom::IResult<&str, Vec<(Columns, LineItem)>> {
+    positioned(input, LineItem::parse)
 }
 
 fn parse_stack_number(input: &str) -> nom::IResult<&str, u64> {
-    tuple((
-        tag(" "),
-        map_res(nom::character::complete::digit1, u64::from_str),
-        tag(" "),
-    ))
-    .map(|(_, n, _)| n)
+    map_res(nom::character::complete::digit1, u64::from_str).parse(input)
+}
+
+/// Parses the footer with the stack numbers.
+fn parse_stack_numbers(input: &str) -> nom::IResult<&str, Vec<(Columns, u64)>> {
+    nom::combinator::verify(
+        |input| positioned(input, parse_stack_number),
+        |numbers: &Vec<_>| !numbers.is_empty(),
+    )
     .parse(input)
 }
 
@@ -124,24 +319,75 @@ fn parse_newlines(input: &str) -> nom::IResult<&str, ()> {
     nom::multi::many1(tag("\n")).map(|_| ()).parse(input)
 }
 
-fn parse_input(input: &str) -> (Vec<Vec<LineItem>>, Vec<u64>, Moves) {
-    // parse the state until an empty line, from there on parse moves
+/// Lines the crates of a row up with the stack numbers of the footer. Each
+/// crate belongs to the closest number, overlapping ones being closest.
+fn align(row: Vec<(Columns, LineItem)>, footer: &[(Columns, u64)]) -> Option<Vec<LineItem>> {
+    let distance = |a: &Columns, b: &Columns| {
+        if a.start >= b.end {
+            a.start - b.end + 1
+        } else {
+            b.start.saturating_sub(a.end - 1)
+        }
+    };
 
-    // each line contains a crate or three whitespaces for an empty spot,
-    // after the parsing we transpose the lines into crates
+    let mut aligned: Vec<LineItem> = (0..footer.len()).map(|_| LineItem::Empty).collect();
+    for (columns, item) in row {
+        let (stack, _) = footer
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (number, _))| distance(&columns, number))?;
+        if aligned[stack] != LineItem::Empty {
+            return None;
+        }
+        aligned[stack] = item;
+    }
+
+    Some(aligned)
+}
 
-    let (rem, cargo) = nom::multi::many1(nom::sequence::terminated(
-        parse_line,
-        nom::character::complete::newline,
+/// Parses the drawing of the stacks up to and including the numbered footer.
+/// The columns of the stacks are taken from the footer.
+fn parse_drawing(input: &str) -> nom::IResult<&str, (Vec<Vec<LineItem>>, Vec<u64>)> {
+    let (rem, (rows, footer)) = tuple((
+        nom::multi::many0(terminated(parse_line, nom::character::complete::newline)),
+        parse_stack_numbers,
     ))
-    .parse(input)
-    .unwrap();
+    .parse(input)?;
+
+    let cargo = rows
+        .into_iter()
+        .map(|row| align(row, &footer))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Verify))
+        })?;
+
+    Ok((rem, (cargo, footer.into_iter().map(|(_, n)| n).collect())))
+}
+
+/// The rows of the drawing, the stack numbers and the moves.
+type Input = (Vec<Vec<LineItem>>, Vec<u64>, Moves);
+
+/// Why a puzzle input couldn't be read.
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    /// the drawing of the stacks or its numbered footer is malformed
+    Drawing,
+    /// the line with this number, counting from 1, isn't a move
+    Moves { line: usize },
+}
 
-    let (rem, numbers): (_, Vec<u64>) = nom::multi::separated_list1(tag(" "), parse_stack_number)
-        .parse(rem)
-        .unwrap();
+fn try_parse_input(input: &str) -> Result<Input, ParseError> {
+    // parse the state until an empty line, from there on parse moves
 
-    let (rem, _) = parse_newlines(rem).unwrap();
+    // each line contains crates or whitespace for an empty spot, after the
+    // parsing we transpose the lines into crates
+
+    let line_of = |rem: &str| input[..input.offset(rem)].lines().count() + 1;
+
+    let (rem, (cargo, numbers)) = parse_drawing(input).map_err(|_| ParseError::Drawing)?;
+
+    let (rem, _) = parse_newlines(rem).map_err(|_| ParseError::Moves { line: line_of(rem) })?;
 
     let (rem, moves) = nom::multi::many1(
         tuple((
@@ -151,9 +397,17 @@ fn parse_input(input: &str) -> (Vec<Vec<LineItem>>, Vec<u64>, Moves) {
         .map(|(a, _)| a),
     )
     .parse(rem)
-    .unwrap();
+    .map_err(|_| ParseError::Moves { line: line_of(rem) })?;
+    if !rem.trim_end().is_empty() {
+        return Err(ParseError::Moves { line: line_of(rem) });
+    }
+
+    Ok((cargo, numbers, Moves { moves }))
+}
 
-    (cargo, numbers, Moves { moves })
+/// `try_parse_input` for the inputs that come with the puzzle.
+fn parse_input(input: &str) -> Input {
+    try_parse_input(input).expect("invalid puzzle input")
 }
 
 fn transpose_cargo(numbers: Vec<u64>, cargo: Vec<Vec<LineItem>>) -> CargoBay {
@@ -169,7 +423,7 @@ fn transpose_cargo(numbers: Vec<u64>, cargo: Vec<Vec<LineItem>>) -> CargoBay {
             assert!(stack_number < num_stacks);
             match item {
                 LineItem::Empty => {}
-                LineItem::Crate(c) => stacks[stack_number].items.push(*c),
+                LineItem::Crate(c) => stacks[stack_number].items.push(c.clone()),
             };
         }
     }
@@ -177,47 +431,164 @@ fn transpose_cargo(numbers: Vec<u64>, cargo: Vec<Vec<LineItem>>) -> CargoBay {
     CargoBay { stacks }
 }
 
-fn main() {
-    println!(
-        "part1: {:?}",
-        part1(include_str!("input.txt"))
-            .into_iter()
-            .fold(String::new(), |s, c| format!("{}{}", s, c.0))
+fn usage() -> ! {
+    eprintln!(
+        "usage: day05
+       day05 [--trace | --json] <crane>
+       day05 debug <crane>
+       day05 animate <crane>
+       day05 optimize <crane>
+       day05 reverse [--tops] <crane> < <final bay and moves>
+       day05 bench
+
+cranes: 9000 | 9001 | capped:<capacity> | alternating"
     );
+    std::process::exit(1);
+}
 
-    println!(
-        "part2: {:?}",
-        part2(include_str!("input.txt"))
+fn main() {
+    let answer = |crates: Vec<Crate>| {
+        crates
             .into_iter()
             .fold(String::new(), |s, c| format!("{}{}", s, c.0))
-    );
+    };
 
-}
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args == ["bench"] {
+        bench::bench();
+        return;
+    }
 
-fn part1(input: &str) -> Vec<Crate> {
-    let (cargo, numbers, moves) = parse_input(input);
-    let mut bay = transpose_cargo(numbers, cargo);
-    for m in moves.moves {
-        bay.execute(m);
-        bay.print();
+    if let ["optimize", name] = args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        let new_crane = || crane::by_name(name).unwrap_or_else(|| usage());
+        let (cargo, numbers, moves) = parse_input(include_str!("input.txt"));
+        let bay = transpose_cargo(numbers, cargo);
+        match optimize::optimize(&bay, &moves.moves, &new_crane, 20_000) {
+            Ok(shorter) => {
+                eprintln!("{} moves instead of {}", shorter.len(), moves.moves.len());
+                println!("{}", bay.render());
+                for m in shorter {
+                    println!("{}", m);
+                }
+            }
+            Err(e) => println!("{}: {:?}", name, e),
+        }
+        return;
+    }
+
+    if let ["reverse", flags @ .., name] = &args.iter().map(String::as_str).collect::<Vec<_>>()[..]
+    {
+        let tops_only = match flags {
+            [] => false,
+            ["--tops"] => true,
+            _ => usage(),
+        };
+        let new_crane = || crane::by_name(name).unwrap_or_else(|| usage());
+        let input = std::io::read_to_string(std::io::stdin()).expect("failed to read stdin");
+        let (cargo, numbers, moves) = match try_parse_input(&input) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{}: {:?}", name, e);
+                return;
+            }
+        };
+        let end = transpose_cargo(numbers, cargo);
+        // with --tops only the topmost crate of each stack is taken as known
+        let start = if tops_only {
+            let tops: Vec<_> = end.stacks.iter().map(|s| s.items.last().cloned()).collect();
+            reverse::reverse_from_tops(&tops, &moves.moves, &new_crane)
+        } else {
+            reverse::reverse(&end, &moves.moves, &new_crane)
+        };
+        match start {
+            Ok(start) => {
+                println!("{}", start.render());
+                for m in moves.moves {
+                    println!("{}", m);
+                }
+            }
+            Err(e) => println!("{}: {:?}", name, e),
+        }
+        return;
+    }
+
+    if let [mode @ ("debug" | "animate"), name] =
+        args.iter().map(String::as_str).collect::<Vec<_>>()[..]
+    {
+        let crane = crane::by_name(name).unwrap_or_else(|| usage());
+        let (cargo, numbers, moves) = parse_input(include_str!("input.txt"));
+        let mut replay = Replay::new(transpose_cargo(numbers, cargo), moves.moves, crane);
+        if mode == "debug" {
+            replay::debug(&mut replay, std::io::stdin().lock(), std::io::stdout())
+                .expect("failed to talk to the terminal");
+        } else if let Err(e) =
+            animate::animate(&mut replay).expect("failed to talk to the terminal")
+        {
+            println!("{}: {:?}", name, e);
+        }
+        return;
+    }
+
+    let mut events: Box<dyn EventSink> = match args.first().map(String::as_str) {
+        Some("--trace") => Box::new(Trace(std::io::stdout())),
+        Some("--json") => Box::new(JsonLines(std::io::stdout())),
+        Some(flag) if flag.starts_with("--") => usage(),
+        _ => Box::new(Quiet),
+    };
+    if args.first().is_some_and(|a| a.starts_with("--")) {
+        args.remove(0);
+        if args.is_empty() {
+            usage();
+        }
+    }
+
+    if let [name] = &args[..] {
+        let mut crane = crane::by_name(name).unwrap_or_else(|| usage());
+        match run(include_str!("input.txt"), crane.as_mut(), events.as_mut()) {
+            Ok(crates) => println!("{}: {:?}", name, answer(crates)),
+            Err(e) => println!("{}: {:?}", name, e),
+        }
+        return;
+    } else if !args.is_empty() {
+        usage();
+    }
+
+    match part1(include_str!("input.txt")) {
+        Ok(crates) => println!("part1: {:?}", answer(crates)),
+        Err(e) => println!("part1: {:?}", e),
+    }
+
+    match part2(include_str!("input.txt")) {
+        Ok(crates) => println!("part2: {:?}", answer(crates)),
+        Err(e) => println!("part2: {:?}", e),
     }
-    bay.top_of_stacks()
 }
 
-fn part2(input: &str) -> Vec<Crate> {
+fn run(
+    input: &str,
+    crane: &mut dyn Crane,
+    events: &mut dyn EventSink,
+) -> Result<Vec<Crate>, MoveError> {
     let (cargo, numbers, moves) = parse_input(input);
     let mut bay = transpose_cargo(numbers, cargo);
     for m in moves.moves {
-        bay.execute_bulk(m);
-        bay.print();
+        bay.try_apply(crane, &m, events)?;
     }
-    bay.top_of_stacks()
+    Ok(bay.top_of_stacks())
+}
+
+fn part1(input: &str) -> Result<Vec<Crate>, MoveError> {
+    run(input, &mut CrateMover9000, &mut Quiet)
+}
+
+fn part2(input: &str) -> Result<Vec<Crate>, MoveError> {
+    run(input, &mut CrateMover9001, &mut Quiet)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const example: &'static str = r#"    [D]    
+    pub const EXAMPLE: &str = r#"    [D]    
 [N] [C]    
 [Z] [M] [P]
  1   2   3 
@@ -227,22 +598,28 @@ move 3 from 1 to 3
 move 2 from 2 to 1
 move 1 from 1 to 2"#;
 
+    /// The starting bay of `EXAMPLE` and its moves.
+    pub fn example_bay() -> (CargoBay, Vec<Move>) {
+        let (cargo, numbers, moves) = parse_input(EXAMPLE);
+        (transpose_cargo(numbers, cargo), moves.moves)
+    }
+
     #[test]
     fn test_example() {
         assert_eq!(
-            super::part1(example),
-            vec![Crate('C'), Crate('M'), Crate('Z')]
+            super::part1(EXAMPLE),
+            Ok(vec![Crate::from("C"), Crate::from("M"), Crate::from("Z")])
         );
 
         assert_eq!(
-            super::part2(example),
-            vec![Crate('M'), Crate('C'), Crate('D')]
+            super::part2(EXAMPLE),
+            Ok(vec![Crate::from("M"), Crate::from("C"), Crate::from("D")])
         );
     }
 
     #[test]
     fn test_parse_example() {
-        let (cargo, numbers, moves) = parse_input(example);
+        let (cargo, numbers, moves) = parse_input(EXAMPLE);
         assert_eq!(
             moves.moves,
             vec![
@@ -274,18 +651,18 @@ move 1 from 1 to 2"#;
             vec![
                 vec![
                     LineItem::Empty,
-                    LineItem::Crate(Crate('D')),
+                    LineItem::Crate(Crate::from("D")),
                     LineItem::Empty,
                 ],
                 vec![
-                    LineItem::Crate(Crate('N')),
-                    LineItem::Crate(Crate('C')),
+                    LineItem::Crate(Crate::from("N")),
+                    LineItem::Crate(Crate::from("C")),
                     LineItem::Empty
                 ],
                 vec![
-                    LineItem::Crate(Crate('Z')),
-                    LineItem::Crate(Crate('M')),
-                    LineItem::Crate(Crate('P'))
+                    LineItem::Crate(Crate::from("Z")),
+                    LineItem::Crate(Crate::from("M")),
+                    LineItem::Crate(Crate::from("P"))
                 ],
             ]
         );
@@ -296,18 +673,18 @@ move 1 from 1 to 2"#;
         let cargo = vec![
             vec![
                 LineItem::Empty,
-                LineItem::Crate(Crate('D')),
+                LineItem::Crate(Crate::from("D")),
                 LineItem::Empty,
             ],
             vec![
-                LineItem::Crate(Crate('N')),
-                LineItem::Crate(Crate('C')),
+                LineItem::Crate(Crate::from("N")),
+                LineItem::Crate(Crate::from("C")),
                 LineItem::Empty,
             ],
             vec![
-                LineItem::Crate(Crate('Z')),
-                LineItem::Crate(Crate('M')),
-                LineItem::Crate(Crate('P')),
+                LineItem::Crate(Crate::from("Z")),
+                LineItem::Crate(Crate::from("M")),
+                LineItem::Crate(Crate::from("P")),
             ],
         ];
         let numbers = vec![1, 2, 3];
@@ -317,19 +694,19 @@ move 1 from 1 to 2"#;
         assert_eq!(
             bay.stacks[0],
             Stack {
-                items: vec![Crate('Z'), Crate('N')]
+                items: vec![Crate::from("Z"), Crate::from("N")]
             }
         );
         assert_eq!(
             bay.stacks[1],
             Stack {
-                items: vec![Crate('M'), Crate('C'), Crate('D')]
+                items: vec![Crate::from("M"), Crate::from("C"), Crate::from("D")]
             }
         );
         assert_eq!(
             bay.stacks[2],
             Stack {
-                items: vec![Crate('P')]
+                items: vec![Crate::from("P")]
             }
         );
     }
@@ -337,63 +714,132 @@ move 1 from 1 to 2"#;
     #[test]
     fn test_parse_line() {
         let line = "    [A]    ";
+        let (rem, line) = parse_line(line).unwrap();
+        assert_eq!(rem, "");
+        assert_eq!(line, vec![(4..7, LineItem::Crate(Crate::from("A")))]);
+
+        let line = "    [A] [BC]";
         let (_, line) = parse_line(line).unwrap();
-        assert_eq!(line.len(), 3);
         assert_eq!(
             line,
             vec![
-                LineItem::Empty,
-                LineItem::Crate(Crate('A')),
-                LineItem::Empty
+                (4..7, LineItem::Crate(Crate::from("A"))),
+                (8..12, LineItem::Crate(Crate::from("BC")))
             ]
         );
 
-        let line = "    [A] [B]";
-        let (_, line) = parse_line(line).unwrap();
-        assert_eq!(line.len(), 3);
+        let footer = " 1   2   3 ";
+        let (_, numbers) = parse_stack_numbers(footer).unwrap();
+        assert_eq!(numbers, vec![(1..2, 1), (5..6, 2), (9..10, 3)]);
         assert_eq!(
-            line,
-            vec![
+            align(line, &numbers),
+            Some(vec![
                 LineItem::Empty,
-                LineItem::Crate(Crate('A')),
-                LineItem::Crate(Crate('B'))
-            ]
+                LineItem::Crate(Crate::from("A")),
+                LineItem::Crate(Crate::from("BC"))
+            ])
         );
     }
 
     #[test]
     fn test_parse_input() {
         let input = "    [A]    \n[B] [C] [D]\n 1   2   3 \n\nmove 1 from 1 to 1";
-        let (cargo, numbers, moves) = super::parse_input(input);
+        let (cargo, numbers, _) = super::parse_input(input);
         assert_eq!(numbers, vec![1, 2, 3]);
         assert_eq!(
             cargo,
             vec![
                 vec![
                     LineItem::Empty,
-                    LineItem::Crate(Crate('A')),
+                    LineItem::Crate(Crate::from("A")),
                     LineItem::Empty
                 ],
                 vec![
-                    LineItem::Crate(Crate('B')),
-                    LineItem::Crate(Crate('C')),
-                    LineItem::Crate(Crate('D'))
+                    LineItem::Crate(Crate::from("B")),
+                    LineItem::Crate(Crate::from("C")),
+                    LineItem::Crate(Crate::from("D"))
                 ],
             ]
         );
     }
 
+    #[test]
+    fn test_parse_input_errors() {
+        let drawing = "[A]\n 1 \n\n";
+        assert!(super::try_parse_input(&format!("{}move 1 from 1 to 2\n", drawing)).is_ok());
+        assert_eq!(
+            super::try_parse_input("[A\n 1 \n\nmove 1 from 1 to 2"),
+            Err(ParseError::Drawing)
+        );
+        assert_eq!(
+            super::try_parse_input(&format!("{}move 1 from 1 to 2\nmove one\n", drawing)),
+            Err(ParseError::Moves { line: 5 })
+        );
+        assert_eq!(
+            super::try_parse_input(&format!("{}nothing\n", drawing)),
+            Err(ParseError::Moves { line: 4 })
+        );
+        assert_eq!(
+            super::try_parse_input("[A]\n 1 \n"),
+            Err(ParseError::Moves { line: 3 })
+        );
+    }
+
     #[test]
     fn test_parse_line_item() {
-        let empty = "   ";
-        let (r, res) = super::LineItem::parse(empty).unwrap();
-        assert_eq!(r, "");
-        assert_eq!(res, super::LineItem::Empty);
+        assert!(super::LineItem::parse("   ").is_err());
+        assert!(super::LineItem::parse("[]").is_err());
 
         let some = "[A]";
         let (r, res) = super::LineItem::parse(some).unwrap();
         assert_eq!(r, "");
-        assert_eq!(res, super::LineItem::Crate(Crate('A')));
+        assert_eq!(res, super::LineItem::Crate(Crate::from("A")));
+
+        let (r, res) = super::LineItem::parse("[XYZ] [B]").unwrap();
+        assert_eq!(r, " [B]");
+        assert_eq!(res, super::LineItem::Crate(Crate::from("XYZ")));
+    }
+
+    #[test]
+    fn test_wide_drawing() {
+        let input = concat!(
+            "                                            [K]\n",
+            "[A]                                     [J] [L]\n",
+            "[B]  [CD]                               [I] [M]\n",
+            " 1    2    3   4   5   6   7   8   9    10   11 \n",
+            "\n",
+            "move 2 from 10 to 2\n",
+            "move 1 from 11 to 10\n",
+        );
+        let (cargo, numbers, moves) = parse_input(input);
+        assert_eq!(numbers, (1..=11).collect::<Vec<_>>());
+        let mut bay = transpose_cargo(numbers, cargo);
+        assert_eq!(bay.stacks[1].items, vec![Crate::from("CD")]);
+        assert_eq!(
+            bay.stacks[9].items,
+            vec![Crate::from("I"), Crate::from("J")]
+        );
+        assert_eq!(
+            bay.stacks[10].items,
+            vec![Crate::from("M"), Crate::from("L"), Crate::from("K")]
+        );
+
+        for m in &moves.moves {
+            bay.try_apply(&mut CrateMover9001, m, &mut Quiet).unwrap();
+        }
+        let drawing = bay.render();
+        assert_eq!(
+            drawing,
+            concat!(
+                "    [J]                                       \n",
+                "[A] [I]                                   [L] \n",
+                "[B] [CD]                             [K]  [M] \n",
+                " 1   2    3   4   5   6   7   8   9   10   11 \n",
+            )
+        );
+
+        let (_, (cargo, numbers)) = parse_drawing(&drawing).unwrap();
+        assert_eq!(transpose_cargo(numbers, cargo), bay);
     }
 
     #[test]
@@ -410,4 +856,128 @@ move 1 from 1 to 2"#;
             }
         );
     }
+
+    #[test]
+    fn test_try_apply_errors() {
+        let (mut bay, _) = example_bay();
+        let before = bay.clone();
+
+        let m = |quantity, from, to| Move { quantity, from, to };
+
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9000, &m(1, 0, 1), &mut Quiet),
+            Err(MoveError::InvalidStack(0))
+        );
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9000, &m(1, 1, 4), &mut Quiet),
+            Err(MoveError::InvalidStack(4))
+        );
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9000, &m(1, 2, 2), &mut Quiet),
+            Err(MoveError::SameStack(2))
+        );
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9001, &m(3, 1, 2), &mut Quiet),
+            Err(MoveError::InsufficientCrates {
+                stack: 1,
+                available: 2,
+                requested: 3
+            })
+        );
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9000, &m(3, 1, 2), &mut Quiet),
+            Err(MoveError::InsufficientCrates {
+                stack: 1,
+                available: 2,
+                requested: 3
+            })
+        );
+        assert_eq!(bay, before);
+
+        assert_eq!(
+            bay.try_apply(&mut CrateMover9000, &m(2, 1, 2), &mut Quiet),
+            Ok(())
+        );
+        assert_eq!(bay.stacks[0].items, vec![]);
+    }
+
+    #[test]
+    fn test_events() {
+        let (mut bay, _) = example_bay();
+        let mut events = vec![];
+
+        let m = Move {
+            quantity: 2,
+            from: 2,
+            to: 1,
+        };
+        bay.try_apply(&mut CrateMover9001, &m, &mut events).unwrap();
+        let failing = Move {
+            quantity: 4,
+            from: 2,
+            to: 1,
+        };
+        assert!(bay
+            .try_apply(&mut CrateMover9001, &failing, &mut events)
+            .is_err());
+
+        assert_eq!(
+            events,
+            vec![
+                Event::MoveStarted(m),
+                Event::CrateLifted {
+                    stack: 2,
+                    item: Crate::from("D")
+                },
+                Event::CrateLifted {
+                    stack: 2,
+                    item: Crate::from("C")
+                },
+                Event::CratePlaced {
+                    stack: 1,
+                    item: Crate::from("C")
+                },
+                Event::CratePlaced {
+                    stack: 1,
+                    item: Crate::from("D")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let (bay, moves) = example_bay();
+        let drawing = bay.render();
+        assert_eq!(
+            drawing,
+            "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n"
+        );
+
+        let (rem, (cargo, numbers)) = parse_drawing(&drawing).unwrap();
+        assert_eq!(rem, "\n");
+        assert_eq!(transpose_cargo(numbers, cargo), bay);
+
+        let mut bay = bay;
+        for m in &moves {
+            bay.try_apply(&mut CrateMover9000, m, &mut Quiet).unwrap();
+            let (_, (cargo, numbers)) = parse_drawing(&bay.render()).unwrap();
+            assert_eq!(transpose_cargo(numbers, cargo), bay);
+        }
+
+        let empty = CargoBay {
+            stacks: vec![Stack { items: vec![] }; 2],
+        };
+        assert_eq!(empty.render(), " 1   2 \n");
+        let (_, (cargo, numbers)) = parse_drawing(&empty.render()).unwrap();
+        assert_eq!(transpose_cargo(numbers, cargo), empty);
+    }
+
+    #[test]
+    fn test_render_input() {
+        let input = include_str!("input.txt");
+        let (cargo, numbers, _) = parse_input(input);
+        let drawing = transpose_cargo(numbers, cargo).render();
+        assert!(input.starts_with(&(drawing + "\n")));
+    }
 }
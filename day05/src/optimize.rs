@@ -0,0 +1,255 @@
+use crate::crane::Crane;
+use crate::events::Quiet;
+use crate::{CargoBay, Move, MoveError};
+use std::collections::{HashMap, VecDeque};
+
+/// Hands out a fresh crane for every simulation, so stateful cranes start
+/// over each time.
+pub type NewCrane<'a> = &'a dyn Fn() -> Box<dyn Crane>;
+
+/// Runs `moves` on a copy of `bay`.
+pub fn simulate(
+    bay: &CargoBay,
+    moves: &[Move],
+    new_crane: NewCrane,
+) -> Result<CargoBay, MoveError> {
+    let mut bay = bay.clone();
+    let mut crane = new_crane();
+    for m in moves {
+        bay.try_apply(crane.as_mut(), m, &mut Quiet)?;
+    }
+    Ok(bay)
+}
+
+/// The rewrites worth trying at `i`, as the number of moves they replace
+/// and what they are replaced with.
+fn candidates(moves: &[Move], i: usize) -> Vec<(usize, Vec<Move>)> {
+    let mut candidates = vec![];
+    let a = moves[i];
+    if a.quantity == 0 {
+        candidates.push((1, vec![]));
+    }
+
+    if let Some(&b) = moves.get(i + 1) {
+        if (a.from, a.to) == (b.from, b.to) {
+            // two moves between the same stacks in a row
+            candidates.push((
+                2,
+                vec![Move {
+                    quantity: a.quantity + b.quantity,
+                    ..a
+                }],
+            ));
+        } else if (a.from, a.to) == (b.to, b.from) {
+            // A → B → A, partial round trips leave the difference behind
+            candidates.push((2, vec![]));
+            if a.quantity > b.quantity {
+                candidates.push((
+                    2,
+                    vec![Move {
+                        quantity: a.quantity - b.quantity,
+                        ..a
+                    }],
+                ));
+            } else if b.quantity > a.quantity {
+                candidates.push((
+                    2,
+                    vec![Move {
+                        quantity: b.quantity - a.quantity,
+                        ..b
+                    }],
+                ));
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Shortens `moves` by merging consecutive moves between the same stacks,
+/// dropping empty moves and A → B → A round trips. Whether such a rewrite
+/// holds depends on the crane, so every one of them is checked by running
+/// the whole program again.
+pub fn peephole(
+    bay: &CargoBay,
+    moves: &[Move],
+    new_crane: NewCrane,
+) -> Result<Vec<Move>, MoveError> {
+    let target = simulate(bay, moves, new_crane)?;
+    let mut moves = moves.to_vec();
+
+    let mut i = 0;
+    while i < moves.len() {
+        let rewrite = candidates(&moves, i)
+            .into_iter()
+            .find_map(|(consumed, replacement)| {
+                let mut candidate = moves[..i].to_vec();
+                candidate.extend(replacement);
+                candidate.extend_from_slice(&moves[i + consumed..]);
+
+                match simulate(bay, &candidate, new_crane) {
+                    Ok(end) if end == target => Some(candidate),
+                    _ => None,
+                }
+            });
+
+        match rewrite {
+            // the previous move might combine with whatever now follows it
+            Some(shorter) => {
+                moves = shorter;
+                i = i.saturating_sub(1);
+            }
+            None => i += 1,
+        }
+    }
+
+    Ok(moves)
+}
+
+/// Finds a shortest program that turns `bay` into `target` with a
+/// breadth-first search over all bay states. That only works out for small
+/// bays, so the search gives up after visiting `max_states` states. Every
+/// step uses a fresh crane, stateful cranes are only supported as far as
+/// the final check agrees.
+pub fn search(
+    bay: &CargoBay,
+    target: &CargoBay,
+    new_crane: NewCrane,
+    max_states: usize,
+) -> Option<Vec<Move>> {
+    // every visited state and the move that first reached it
+    let mut seen: HashMap<CargoBay, Option<(CargoBay, Move)>> = HashMap::new();
+    let mut queue = VecDeque::from([bay.clone()]);
+    seen.insert(bay.clone(), None);
+
+    while let Some(state) = queue.pop_front() {
+        if state == *target {
+            let mut moves = vec![];
+            let mut current = &state;
+            while let Some(Some((previous, m))) = seen.get(current) {
+                moves.push(*m);
+                current = previous;
+            }
+            moves.reverse();
+
+            return match simulate(bay, &moves, new_crane) {
+                Ok(end) if end == *target => Some(moves),
+                _ => None,
+            };
+        }
+
+        for from in 1..=state.stacks.len() {
+            for to in (1..=state.stacks.len()).filter(|to| *to != from) {
+                for quantity in 1..=state.stacks[from - 1].items.len() {
+                    let m = Move { from, to, quantity };
+                    let mut next = state.clone();
+                    next.try_apply(new_crane().as_mut(), &m, &mut Quiet)
+                        .expect("only generating valid moves");
+                    if seen.contains_key(&next) {
+                        continue;
+                    }
+                    if seen.len() >= max_states {
+                        return None;
+                    }
+                    seen.insert(next.clone(), Some((state.clone(), m)));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Runs the peephole pass and, if the search finds something shorter
+/// within `max_states` states, takes that instead.
+pub fn optimize(
+    bay: &CargoBay,
+    moves: &[Move],
+    new_crane: NewCrane,
+    max_states: usize,
+) -> Result<Vec<Move>, MoveError> {
+    let shorter = peephole(bay, moves, new_crane)?;
+    let target = simulate(bay, moves, new_crane)?;
+
+    Ok(match search(bay, &target, new_crane, max_states) {
+        Some(shortest) if shortest.len() < shorter.len() => shortest,
+        _ => shorter,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{AlternatingCrane, CrateMover9000, CrateMover9001};
+    use crate::tests::example_bay;
+
+    fn m(quantity: usize, from: usize, to: usize) -> Move {
+        Move { quantity, from, to }
+    }
+
+    #[test]
+    fn test_peephole_9000() {
+        let new_crane: NewCrane = &|| Box::new(CrateMover9000);
+        let moves = vec![
+            m(1, 2, 1),
+            m(1, 2, 1),
+            m(0, 3, 1),
+            m(2, 1, 3),
+            m(2, 3, 1),
+            m(1, 1, 2),
+        ];
+
+        // after merging and dropping the round trip, putting one of the two
+        // crates back is the same as only moving the other one
+        let (bay, _) = example_bay();
+        let shorter = peephole(&bay, &moves, new_crane).unwrap();
+        assert_eq!(shorter, vec![m(1, 2, 1)]);
+    }
+
+    #[test]
+    fn test_peephole_9001() {
+        let new_crane: NewCrane = &|| Box::new(CrateMover9001);
+        // the crane keeps the order, so two single moves aren't a double move
+        let moves = vec![m(1, 2, 1), m(1, 2, 1), m(2, 1, 3), m(2, 3, 1)];
+
+        let (bay, _) = example_bay();
+        let shorter = peephole(&bay, &moves, new_crane).unwrap();
+        assert_eq!(shorter, vec![m(1, 2, 1), m(1, 2, 1)]);
+        assert_eq!(
+            simulate(&bay, &shorter, new_crane),
+            simulate(&bay, &moves, new_crane)
+        );
+    }
+
+    #[test]
+    fn test_peephole_stateful() {
+        let new_crane: NewCrane = &|| Box::<AlternatingCrane>::default();
+        let moves = vec![m(1, 2, 1), m(1, 2, 1), m(2, 1, 3)];
+
+        // merging the first two moves would flip how the last one is done
+        let (bay, _) = example_bay();
+        let shorter = peephole(&bay, &moves, new_crane).unwrap();
+        assert_eq!(shorter, moves);
+
+        let shorter = peephole(&bay, &moves, &|| Box::new(CrateMover9000)).unwrap();
+        assert_eq!(shorter, vec![m(2, 2, 1), m(2, 1, 3)]);
+    }
+
+    #[test]
+    fn test_search() {
+        let new_crane: NewCrane = &|| Box::new(CrateMover9000);
+        let (bay, moves) = example_bay();
+
+        let shortest = optimize(&bay, &moves, new_crane, 100_000).unwrap();
+        assert!(shortest.len() < moves.len());
+        assert_eq!(
+            simulate(&bay, &shortest, new_crane),
+            simulate(&bay, &moves, new_crane)
+        );
+
+        assert_eq!(search(&bay, &bay, new_crane, 1), Some(vec![]));
+        let target = simulate(&bay, &moves, new_crane).unwrap();
+        assert_eq!(search(&bay, &target, new_crane, 10), None);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::crane::Crane;
+use crate::events::Quiet;
+use crate::{CargoBay, Crate, Move, MoveError};
+use std::io::{BufRead, Write};
+
+/// What a single executed move changed on the bay. That is enough to undo
+/// and redo it without asking the crane again.
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    from: usize,
+    to: usize,
+    // bottom to top, as they were on the source stack
+    lifted: Vec<Crate>,
+    // bottom to top, as they are on the destination stack
+    placed: Vec<Crate>,
+}
+
+/// A crane program running on a bay that can be stepped forward and backward.
+pub struct Replay {
+    bay: CargoBay,
+    moves: Vec<Move>,
+    crane: Box<dyn Crane>,
+    // every move executed so far, the first `position` of them are applied
+    log: Vec<Step>,
+    position: usize,
+}
+
+impl Replay {
+    pub fn new(bay: CargoBay, moves: Vec<Move>, crane: Box<dyn Crane>) -> Self {
+        Replay {
+            bay,
+            moves,
+            crane,
+            log: vec![],
+            position: 0,
+        }
+    }
+
+    pub fn bay(&self) -> &CargoBay {
+        &self.bay
+    }
+
+    /// Number of moves applied to the bay.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// The moves that haven't been applied yet.
+    pub fn remaining(&self) -> &[Move] {
+        &self.moves[self.position..]
+    }
+
+    /// The current state as a puzzle input: the drawing of the bay followed
+    /// by the remaining moves.
+    pub fn snapshot(&self) -> String {
+        let mut out = self.bay.render();
+        out.push('\n');
+        for m in self.remaining() {
+            out += &format!("{}\n", m);
+        }
+        out
+    }
+
+    /// Applies the next move, returns false at the end of the program.
+    pub fn step_forward(&mut self) -> Result<bool, MoveError> {
+        if self.position == self.moves.len() {
+            return Ok(false);
+        }
+
+        if let Some(step) = self.log.get(self.position) {
+            let (from, to) = self.bay.two_stacks(step.from, step.to);
+            from.items.truncate(from.items.len() - step.lifted.len());
+            to.items.extend_from_slice(&step.placed);
+        } else {
+            // the crane only ever sees each move once and in order, which
+            // keeps stateful cranes honest
+            let m = self.moves[self.position];
+            self.bay.validate(&m)?;
+            let (from, _) = self.bay.two_stacks(m.from, m.to);
+            let lifted = from.items[from.items.len() - m.quantity..].to_vec();
+            self.bay.try_apply(self.crane.as_mut(), &m, &mut Quiet)?;
+            let (_, to) = self.bay.two_stacks(m.from, m.to);
+            let placed = to.items[to.items.len() - m.quantity..].to_vec();
+            self.log.push(Step {
+                from: m.from,
+                to: m.to,
+                lifted,
+                placed,
+            });
+        }
+
+        self.position += 1;
+        Ok(true)
+    }
+
+    /// Reverts the last applied move, returns false at the start.
+    pub fn step_back(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+
+        self.position -= 1;
+        let step = &self.log[self.position];
+        let (from, to) = self.bay.two_stacks(step.from, step.to);
+        to.items.truncate(to.items.len() - step.placed.len());
+        from.items.extend_from_slice(&step.lifted);
+        true
+    }
+
+    /// Moves to the state after the first `n` moves.
+    pub fn goto(&mut self, n: usize) -> Result<(), MoveError> {
+        let n = n.min(self.moves.len());
+        while self.position > n {
+            self.step_back();
+        }
+        while self.position < n {
+            self.step_forward()?;
+        }
+        Ok(())
+    }
+
+    /// Finds the smallest number of moves after which `pred` holds. Crates
+    /// come and go, so this walks forward from the start instead of
+    /// bisecting; moves that were run before are replayed from the log. The
+    /// replay is left at the found position, or at the end.
+    pub fn find(&mut self, pred: impl Fn(&CargoBay) -> bool) -> Result<Option<usize>, MoveError> {
+        self.goto(0)?;
+        loop {
+            if pred(&self.bay) {
+                return Ok(Some(self.position));
+            }
+            if !self.step_forward()? {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Runs the interactive debugger: reads commands line by line from `input`
+/// and writes the answers to `out`.
+pub fn debug(replay: &mut Replay, input: impl BufRead, mut out: impl Write) -> std::io::Result<()> {
+    const HELP: &str =
+        "commands: show | dump | next | back | goto <n> | find <crate> <stack> | quit";
+
+    writeln!(out, "{}", HELP)?;
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => continue,
+            ["q"] | ["quit"] => break,
+            ["s"] | ["show"] => Ok(()),
+            ["dump"] => {
+                write!(out, "{}", replay.snapshot())?;
+                continue;
+            }
+            ["n"] | ["next"] => replay.step_forward().map(|_| ()),
+            ["b"] | ["back"] => {
+                replay.step_back();
+                Ok(())
+            }
+            ["g", n] | ["goto", n] => match n.parse() {
+                Ok(n) => replay.goto(n),
+                Err(_) => {
+                    writeln!(out, "not a move number: {}", n)?;
+                    continue;
+                }
+            },
+            // `bisect` is the old name, it is still understood
+            ["f" | "find" | "bisect", label, stack] => {
+                let (label, stack) = match stack.parse::<usize>() {
+                    Ok(stack) if stack > 0 => (Crate::from(label), stack),
+                    _ => {
+                        writeln!(out, "usage: find <crate> <stack>")?;
+                        continue;
+                    }
+                };
+                let found = replay.find(|bay| {
+                    bay.stacks
+                        .get(stack - 1)
+                        .is_some_and(|s| s.items.contains(&label))
+                });
+                match found {
+                    Ok(Some(n)) => {
+                        writeln!(out, "[{}] is on stack {} after move {}", label.0, stack, n)?
+                    }
+                    Ok(None) => writeln!(out, "[{}] never reaches stack {}", label.0, stack)?,
+                    Err(_) => {}
+                }
+                found.map(|_| ())
+            }
+            _ => {
+                writeln!(out, "{}", HELP)?;
+                continue;
+            }
+        };
+
+        if let Err(e) = result {
+            writeln!(out, "error: {:?}", e)?;
+        }
+        writeln!(out, "after move {}/{}:", replay.position(), replay.len())?;
+        write!(out, "{}", replay.bay())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{AlternatingCrane, CrateMover9000};
+    use crate::tests::example_bay;
+    use crate::{parse_input, transpose_cargo};
+
+    fn replay(crane: Box<dyn Crane>) -> Replay {
+        let (bay, moves) = example_bay();
+        Replay::new(bay, moves, crane)
+    }
+
+    fn bay_after(n: usize, crane: Box<dyn Crane>) -> CargoBay {
+        let mut r = replay(crane);
+        for _ in 0..n {
+            r.step_forward().unwrap();
+        }
+        r.bay
+    }
+
+    #[test]
+    fn test_step_back_and_forth() {
+        let mut r = replay(Box::<AlternatingCrane>::default());
+        let start = r.bay.clone();
+
+        r.goto(4).unwrap();
+        assert!(!r.step_forward().unwrap());
+        assert_eq!(
+            r.bay.top_of_stacks(),
+            bay_after(4, Box::<AlternatingCrane>::default()).top_of_stacks()
+        );
+
+        for n in (0..4).rev() {
+            assert!(r.step_back());
+            assert_eq!(r.position(), n);
+            assert_eq!(r.bay, bay_after(n, Box::<AlternatingCrane>::default()));
+        }
+        assert!(!r.step_back());
+        assert_eq!(r.bay, start);
+
+        // replaying doesn't hand the moves to the crane again
+        r.goto(3).unwrap();
+        assert_eq!(r.bay, bay_after(3, Box::<AlternatingCrane>::default()));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut r = replay(Box::new(CrateMover9000));
+        let on = |label: &'static str, stack: usize| {
+            move |bay: &CargoBay| bay.stacks[stack - 1].items.contains(&Crate::from(label))
+        };
+
+        assert_eq!(r.find(on("D", 3)).unwrap(), Some(2));
+        assert_eq!(r.position(), 2);
+        assert_eq!(r.find(on("P", 3)).unwrap(), Some(0));
+        assert_eq!(r.find(on("P", 1)).unwrap(), None);
+        assert_eq!(r.position(), 4);
+
+        // crates that visit a stack and leave it again: D is on stack 1
+        // after the first move only, Z starts there and is gone at the end
+        assert_eq!(r.find(on("D", 1)).unwrap(), Some(1));
+        assert_eq!(r.position(), 1);
+        assert_eq!(r.find(on("Z", 1)).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut r = replay(Box::new(CrateMover9000));
+        let mut out = vec![];
+        debug(
+            &mut r,
+            "goto 2\nback\nfind D 3\nbisect D 1\nquit\nnext\n".as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("after move 2/4:"));
+        assert!(out.contains("after move 1/4:"));
+        assert!(out.contains("[D] is on stack 3 after move 2"));
+        assert!(out.contains("[D] is on stack 1 after move 1"));
+        assert_eq!(r.position(), 1);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut r = replay(Box::new(CrateMover9000));
+        r.goto(2).unwrap();
+        let snapshot = r.snapshot();
+        assert_eq!(
+            snapshot,
+            "        [Z]\n        [N]\n    [C] [D]\n    [M] [P]\n 1   2   3 \n\nmove 2 from 2 to 1\nmove 1 from 1 to 2\n"
+        );
+
+        // the snapshot is a valid input that ends up where the full run does
+        let (cargo, numbers, moves) = parse_input(&snapshot);
+        let mut resumed = Replay::new(
+            transpose_cargo(numbers, cargo),
+            moves.moves,
+            Box::new(CrateMover9000),
+        );
+        resumed.goto(2).unwrap();
+        r.goto(4).unwrap();
+        assert_eq!(resumed.bay, r.bay);
+    }
+}
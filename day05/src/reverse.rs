@@ -0,0 +1,222 @@
+use crate::crane::Crane;
+use crate::optimize::{simulate, NewCrane};
+use crate::{CargoBay, Crate, Move, MoveError, Stack};
+
+/// Why no initial bay could be found.
+#[derive(Debug, PartialEq)]
+pub enum ReverseError {
+    /// the move with this index can't have led to the bay as it is after it
+    Undo { index: usize, error: MoveError },
+    /// running the moves forward from the found bay ends somewhere else,
+    /// the crane doesn't move crates the same way every time
+    Diverged,
+}
+
+/// Stands in for a crate nobody knows anything about. It is drawn as `[?]`,
+/// so a found bay can be read back in.
+pub fn unknown() -> Crate {
+    Crate::from("?")
+}
+
+/// Where each crate of a move ends up: entry `i` is the position, bottom to
+/// top, on the source stack of the crate that lands at position `i` on the
+/// destination. Found by letting the crane move numbered crates.
+fn arrangement(crane: &mut dyn Crane, quantity: usize) -> Vec<usize> {
+    let mut from = Stack {
+        items: (0..quantity).map(|i| Crate(i.to_string())).collect(),
+    };
+    let mut to = Stack { items: vec![] };
+    crane.transfer(&mut from, &mut to, quantity);
+    to.items
+        .iter()
+        .map(|c| {
+            c.0.parse()
+                .expect("the crane only moves the numbered crates")
+        })
+        .collect()
+}
+
+/// Takes back `m` from `bay`, given how the crane arranged the crates.
+fn undo(bay: &mut CargoBay, m: &Move, arrangement: &[usize]) -> Result<(), MoveError> {
+    bay.validate(&Move {
+        quantity: m.quantity,
+        from: m.to,
+        to: m.from,
+    })?;
+
+    let (from, to) = bay.two_stacks(m.from, m.to);
+    let mut lifted = vec![None; m.quantity];
+    for (c, &i) in to.lift(m.quantity).zip(arrangement) {
+        lifted[i] = Some(c);
+    }
+    from.items.extend(
+        lifted
+            .into_iter()
+            .map(|c| c.expect("every crate is placed once")),
+    );
+    Ok(())
+}
+
+/// Finds the bay that `moves` turned into `end` and checks it by running the
+/// moves forward again.
+pub fn reverse(
+    end: &CargoBay,
+    moves: &[Move],
+    new_crane: NewCrane,
+) -> Result<CargoBay, ReverseError> {
+    // stateful cranes have to see the moves in order, so ask for all the
+    // arrangements up front
+    let mut crane = new_crane();
+    let arrangements: Vec<_> = moves
+        .iter()
+        .map(|m| arrangement(crane.as_mut(), m.quantity))
+        .collect();
+
+    let mut bay = end.clone();
+    for (index, (m, arrangement)) in moves.iter().zip(&arrangements).enumerate().rev() {
+        undo(&mut bay, m, arrangement).map_err(|error| ReverseError::Undo { index, error })?;
+    }
+
+    match simulate(&bay, moves, new_crane) {
+        Ok(forward) if forward == *end => Ok(bay),
+        _ => Err(ReverseError::Diverged),
+    }
+}
+
+/// Like `reverse` but only the top crate of every stack is known, `None`
+/// for stacks that end up empty. Crates that can't be told from that are
+/// `unknown()`, and stacks are assumed to be as low as the moves allow.
+pub fn reverse_from_tops(
+    tops: &[Option<Crate>],
+    moves: &[Move],
+    new_crane: NewCrane,
+) -> Result<CargoBay, ReverseError> {
+    // going backwards, how far each stack has grown compared to the end and
+    // how high it must have been at the end for every move to be possible
+    let mut grown = vec![0isize; tops.len()];
+    let mut needed = vec![0isize; tops.len()];
+    for m in moves.iter().rev() {
+        let (from, to) = (m.from.wrapping_sub(1), m.to.wrapping_sub(1));
+        if from >= tops.len() || to >= tops.len() {
+            // undoing the move reports it
+            break;
+        }
+        needed[to] = needed[to].max(m.quantity as isize - grown[to]);
+        grown[to] -= m.quantity as isize;
+        grown[from] += m.quantity as isize;
+    }
+
+    let end = CargoBay {
+        stacks: tops
+            .iter()
+            .zip(needed)
+            .map(|(top, needed)| match top {
+                Some(c) => {
+                    let mut items = vec![unknown(); needed.max(1) as usize - 1];
+                    items.push(c.clone());
+                    Stack { items }
+                }
+                None => Stack { items: vec![] },
+            })
+            .collect(),
+    };
+
+    reverse(&end, moves, new_crane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{by_name, CrateMover9000};
+    use crate::tests::example_bay;
+    use crate::{transpose_cargo, try_parse_input};
+
+    #[test]
+    fn test_reverse() {
+        let (start, moves) = example_bay();
+        for name in ["9000", "9001", "alternating", "capped:2"] {
+            let new_crane: NewCrane = &|| by_name(name).unwrap();
+            let end = simulate(&start, &moves, new_crane).unwrap();
+            assert_eq!(
+                reverse(&end, &moves, new_crane),
+                Ok(start.clone()),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_reverse_impossible() {
+        let (_, moves) = example_bay();
+        let new_crane: NewCrane = &|| Box::new(CrateMover9000);
+        let empty = CargoBay {
+            stacks: vec![Stack { items: vec![] }; 3],
+        };
+
+        // the last move puts a crate on stack 2, which ends up empty
+        assert_eq!(
+            reverse(&empty, &moves, new_crane),
+            Err(ReverseError::Undo {
+                index: 3,
+                error: MoveError::InsufficientCrates {
+                    stack: 2,
+                    available: 0,
+                    requested: 1
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_reverse_from_tops() {
+        let (start, moves) = example_bay();
+        let new_crane: NewCrane = &|| Box::new(CrateMover9000);
+        let tops = ["C", "M", "Z"].map(|c| Some(Crate::from(c)));
+
+        let found = reverse_from_tops(&tops, &moves, new_crane).unwrap();
+        let end = simulate(&found, &moves, new_crane).unwrap();
+        assert_eq!(
+            end.top_of_stacks(),
+            tops.iter().flatten().cloned().collect::<Vec<_>>()
+        );
+
+        // whatever could be told matches the real start
+        for (found, real) in found.stacks.iter().zip(&start.stacks) {
+            assert!(found.items.len() <= real.items.len());
+            for (f, r) in found.items.iter().zip(&real.items) {
+                assert!(*f == unknown() || f == r);
+            }
+        }
+        assert!(found
+            .stacks
+            .iter()
+            .flat_map(|s| &s.items)
+            .any(|c| *c != unknown()));
+
+        // stack 2 ends up with crates, it can't be empty
+        let tops = [Some(Crate::from("C")), None, Some(Crate::from("Z"))];
+        assert!(matches!(
+            reverse_from_tops(&tops, &moves, new_crane),
+            Err(ReverseError::Undo { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_reads_back() {
+        let (_, moves) = example_bay();
+        let new_crane: NewCrane = &|| Box::new(CrateMover9000);
+        let tops = ["C", "M", "Z"].map(|c| Some(Crate::from(c)));
+        let found = reverse_from_tops(&tops, &moves, new_crane).unwrap();
+        assert!(found.stacks.iter().any(|s| s.items.contains(&unknown())));
+
+        // the bay and moves as `reverse --tops` prints them
+        let mut printed = found.render() + "\n";
+        for m in &moves {
+            printed += &format!("{}\n", m);
+        }
+        let (cargo, numbers, read) = try_parse_input(&printed).unwrap();
+        assert_eq!(transpose_cargo(numbers, cargo), found);
+        assert_eq!(read.moves, moves);
+    }
+}
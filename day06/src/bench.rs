@@ -0,0 +1,50 @@
+use crate::has_duplicates;
+use aoc_utils::{find_any_window, find_distinct_window, XorShift};
+use std::time::{Duration, Instant};
+
+/// `len` random letters from `a` up to `letters` letters later, generated
+/// from `seed`.
+pub fn generate(len: usize, letters: u8, seed: u64) -> String {
+    let mut rng = XorShift::new(seed);
+    (0..len)
+        .map(|_| (b'a' + rng.below(letters as u64) as u8) as char)
+        .collect()
+}
+
+/// The start-of-message search as it used to be, every window is checked
+/// from scratch.
+fn find_message_by_windows(input: &str, w: usize) -> Option<usize> {
+    (w..=input.len()).find(|&end| !has_duplicates(&input[end - w..end]))
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = f();
+    (start.elapsed(), result)
+}
+
+/// Times the searches for a start-of-message marker on 100 MB that don't
+/// contain one, so every search has to go through all of it.
+pub fn bench() {
+    const W: usize = 14;
+    let lowercase = generate(100_000_000, 13, 2022);
+    // a single capital at the start sends the search down the generic path
+    let mixed = format!("A{}", &lowercase[1..]);
+
+    let (before, expected) = time(|| find_message_by_windows(&lowercase, W));
+    println!("has_duplicates per window: {:>8.2?}", before);
+
+    for (name, input) in [("lowercase", &lowercase), ("mixed", &mixed)] {
+        let (generic, a) = time(|| find_any_window(input.as_bytes(), W));
+        let (fast, b) = time(|| find_distinct_window(input.as_bytes(), W));
+        assert!(a == expected && b == expected, "the searches disagree");
+        println!(
+            "{:<9} generic: {:>8.2?} ({:.1}x), find_distinct_window: {:>8.2?} ({:.1}x)",
+            name,
+            generic,
+            before.as_secs_f64() / generic.as_secs_f64(),
+            fast,
+            before.as_secs_f64() / fast.as_secs_f64()
+        );
+    }
+}
@@ -1,95 +1,375 @@
-use itertools::Itertools;
+mod bench;
 
+use aoc_utils::{
+    distinct_profile, distinct_windows, find_distinct_window, longest_distinct,
+    read_distinct_window,
+};
+use std::io::Write;
 
-fn find_marker(input: &str) -> usize {
-    for (n, (a,b,c,d)) in input.chars().tuple_windows().enumerate() {
-	println!("{} {} {} {}", a, b, c, d);
-	if a != b && b != c && c != d
-	    && a != b && a != c && a != d
-	    && b != d {
-		return n + 4;
-	    }
-    }
-
-    input.len()
+/// The position right after the first start-of-packet marker, if there is
+/// one.
+fn find_marker(input: &str) -> Option<usize> {
+    find_distinct_window(input.as_bytes(), 4)
 }
 
+/// The straightforward check of a single window, kept to compare the window
+/// search against.
 fn has_duplicates(input: &str) -> bool {
     let chars = input.as_bytes();
     for i in 0..input.len() {
-	for x in 0..input.len() {
-	    if x == i {
-		continue
-	    }
+        for x in 0..input.len() {
+            if x == i {
+                continue;
+            }
+
+            if chars[x] == chars[i] {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The position right after the first start-of-message marker, if there is
+/// one.
+fn find_message(input: &str) -> Option<usize> {
+    find_distinct_window(input.as_bytes(), 14)
+}
+
+/// How the markers are spread over a datastream.
+#[derive(Debug, PartialEq, Default)]
+struct MarkerStats {
+    count: usize,
+    /// the most markers in a row, one right after the other
+    longest_run: usize,
+    /// the distances between neighbouring markers
+    gaps: Vec<usize>,
+}
 
-	    if chars[x] == chars[i] {
-		return true;
-	    }
-	}
+impl MarkerStats {
+    fn new(markers: impl Iterator<Item = usize>) -> Self {
+        let mut stats = MarkerStats::default();
+        let mut previous = None;
+        let mut run = 0;
+        for end in markers {
+            stats.count += 1;
+            match previous {
+                Some(p) => {
+                    stats.gaps.push(end - p);
+                    run = if end - p == 1 { run + 1 } else { 1 };
+                }
+                None => run = 1,
+            }
+            stats.longest_run = stats.longest_run.max(run);
+            previous = Some(end);
+        }
+        stats
     }
-    return false;
 }
 
-fn find_message(input: &str) -> usize {
-    let search_length = 14;
-    let start = 0;
-    for i in start..input.len() {
-	if input.len() - i < search_length {
-	    return 0;
-	}
-	let candidate = &input[i..(i+search_length).clamp(0, input.len())];
-	if !has_duplicates(candidate) {
-	    return i + search_length;
-	}
+impl std::fmt::Display for MarkerStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "markers: {}", self.count)?;
+        writeln!(f, "longest run: {}", self.longest_run)?;
+        match (self.gaps.iter().min(), self.gaps.iter().max()) {
+            (Some(min), Some(max)) => writeln!(
+                f,
+                "gaps: min {}, max {}, mean {:.1}",
+                min,
+                max,
+                self.gaps.iter().sum::<usize>() as f64 / self.gaps.len() as f64
+            ),
+            _ => writeln!(f, "gaps: none"),
+        }
     }
+}
 
-    0
+/// Writes a distinct-count profile as CSV, one row per position right after
+/// a byte, like the marker positions.
+fn write_profile(mut out: impl Write, profile: &[usize]) -> std::io::Result<()> {
+    writeln!(out, "position,distinct")?;
+    for (i, distinct) in profile.iter().enumerate() {
+        writeln!(out, "{},{}", i + 1, distinct)?;
+    }
+    Ok(())
 }
 
-fn part1(input: &str) -> usize {
+fn part1(input: &str) -> Option<usize> {
     find_marker(input)
 }
 
-fn part2(input: &str) -> usize {
+fn part2(input: &str) -> Option<usize> {
     find_message(input)
 }
 
+fn usage() -> ! {
+    eprintln!(
+        "usage: day06
+       day06 stream <window size> < <datastream>
+       day06 markers <window size> [--non-overlapping]
+       day06 longest
+       day06 profile <window size>
+       day06 bench"
+    );
+    std::process::exit(1);
+}
+
 fn main() {
-    println!("part1: {}", part1(include_str!("input.txt")));
-    println!("part2: {}", part2(include_str!("input.txt")));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let ["stream", w] = args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        let w = w.parse().unwrap_or_else(|_| usage());
+        match read_distinct_window(std::io::stdin().lock(), w) {
+            Ok(Some(offset)) => println!("{}", offset),
+            Ok(None) => println!("no marker found"),
+            Err(e) => {
+                eprintln!("failed to read the datastream: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    } else if let ["markers", w, flags @ ..] =
+        &args.iter().map(String::as_str).collect::<Vec<_>>()[..]
+    {
+        let w = w.parse().unwrap_or_else(|_| usage());
+        let input = include_str!("input.txt").trim_end();
+        let markers = distinct_windows(input.as_bytes(), w);
+        let stats = match flags {
+            [] => MarkerStats::new(markers),
+            ["--non-overlapping"] => MarkerStats::new(markers.non_overlapping()),
+            _ => usage(),
+        };
+        print!("{}", stats);
+        return;
+    } else if args == ["bench"] {
+        bench::bench();
+        return;
+    } else if args == ["longest"] {
+        let input = include_str!("input.txt").trim_end();
+        let longest = longest_distinct(input.as_bytes());
+        println!(
+            "{} distinct from {} to {}: {}",
+            longest.len(),
+            longest.start,
+            longest.end,
+            String::from_utf8_lossy(&input.as_bytes()[longest.clone()])
+        );
+        return;
+    } else if let ["profile", w] = args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        let w = w.parse().unwrap_or_else(|_| usage());
+        let profile = distinct_profile(include_str!("input.txt").trim_end().as_bytes(), w);
+        write_profile(std::io::stdout().lock(), &profile).expect("failed to write the profile");
+        return;
+    } else if !args.is_empty() {
+        usage();
+    }
+
+    let answer = |marker: Option<usize>| match marker {
+        Some(end) => end.to_string(),
+        None => "no marker found".to_string(),
+    };
+    println!("part1: {}", answer(part1(include_str!("input.txt"))));
+    println!("part2: {}", answer(part2(include_str!("input.txt"))));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_utils::find_any_window;
 
     #[test]
     fn test_find_marker() {
-	assert_eq!(find_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), 7);
-        assert_eq!(find_marker("bvwbjplbgvbhsrlpgdmjqwftvncz"), 5);
-        assert_eq!(find_marker("nppdvjthqldpwncqszvftbrmjlhg"), 6);
-	assert_eq!(find_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 10);
-	assert_eq!(find_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 11);
+        assert_eq!(find_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), Some(7));
+        assert_eq!(find_marker("bvwbjplbgvbhsrlpgdmjqwftvncz"), Some(5));
+        assert_eq!(find_marker("nppdvjthqldpwncqszvftbrmjlhg"), Some(6));
+        assert_eq!(find_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), Some(10));
+        assert_eq!(find_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), Some(11));
+    }
+
+    #[test]
+    fn test_no_marker() {
+        assert_eq!(find_marker(""), None);
+        assert_eq!(find_message(""), None);
+
+        // shorter than the window, even though all characters differ
+        assert_eq!(find_marker("abc"), None);
+        assert_eq!(find_message("abcdefghijklm"), None);
+
+        assert_eq!(find_marker("abcabcabcabc"), None);
+        assert_eq!(find_message("abcdefghijklmabcdefghijklm"), None);
+        assert_eq!(part1("aabbaabb"), None);
+        assert_eq!(part2("mjqjpqmgbljsph"), None);
     }
 
     #[test]
     fn test_has_duplicates() {
-	assert!(has_duplicates("abcdefgha"));
-	assert!(!has_duplicates("abcdefgh"));
-	assert!(!has_duplicates("abcedfghijklmnopqrstuvw"));
+        assert!(has_duplicates("abcdefgha"));
+        assert!(!has_duplicates("abcdefgh"));
+        assert!(!has_duplicates("abcedfghijklmnopqrstuvw"));
     }
-    
+
     #[test]
     fn test_find_message() {
-	assert_eq!(find_message("1234567890abcdefghijklmn"), 14);
-	assert_eq!(find_message("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), 19);
-        assert_eq!(find_message("bvwbjplbgvbhsrlpgdmjqwftvncz"), 23);
-        assert_eq!(find_message("nppdvjthqldpwncqszvftbrmjlhg"), 23);
-	assert_eq!(find_message("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 29);
-	assert_eq!(find_message("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 26);
+        assert_eq!(find_message("1234567890abcdefghijklmn"), Some(14));
+        assert_eq!(find_message("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), Some(19));
+        assert_eq!(find_message("bvwbjplbgvbhsrlpgdmjqwftvncz"), Some(23));
+        assert_eq!(find_message("nppdvjthqldpwncqszvftbrmjlhg"), Some(23));
+        assert_eq!(find_message("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), Some(29));
+        assert_eq!(find_message("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), Some(26));
+    }
+
+    #[test]
+    fn test_find_distinct_window() {
+        let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
+        for w in 0..=input.len() {
+            let expected = (w..=input.len()).find(|&end| !has_duplicates(&input[end - w..end]));
+            assert_eq!(find_distinct_window(input.as_bytes(), w), expected, "{}", w);
+        }
+        assert_eq!(find_distinct_window(b"", 0), Some(0));
+        assert_eq!(find_distinct_window(b"", 1), None);
+    }
+
+    #[test]
+    fn test_distinct_windows() {
+        let input = b"abcabcdd";
+        let all: Vec<_> = distinct_windows(input, 3).collect();
+        assert_eq!(all, vec![3, 4, 5, 6, 7]);
+        assert_eq!(
+            distinct_windows(input, 3)
+                .non_overlapping()
+                .collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        assert_eq!(distinct_windows(input, 0).count(), input.len() + 1);
+        assert_eq!(distinct_windows(input, 5).next(), None);
+
+        for input in [
+            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+            "nppdvjthqldpwncqszvftbrmjlhg",
+        ] {
+            assert_eq!(
+                distinct_windows(input.as_bytes(), 4).next(),
+                find_marker(input)
+            );
+        }
+    }
+
+    #[test]
+    fn test_marker_stats() {
+        let stats = MarkerStats::new(distinct_windows(b"abcabcddabc", 3));
+        assert_eq!(
+            stats,
+            MarkerStats {
+                count: 7,
+                longest_run: 5,
+                gaps: vec![1, 1, 1, 1, 3, 1],
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "markers: 7\nlongest run: 5\ngaps: min 1, max 3, mean 1.3\n"
+        );
+        assert_eq!(
+            MarkerStats::new(std::iter::empty()).to_string(),
+            "markers: 0\nlongest run: 0\ngaps: none\n"
+        );
+    }
+
+    #[test]
+    fn test_longest_distinct() {
+        assert_eq!(longest_distinct(b""), 0..0);
+        assert_eq!(longest_distinct(b"aaaa"), 0..1);
+        assert_eq!(longest_distinct(b"abcabcdab"), 3..7);
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let longest = longest_distinct(input.as_bytes());
+        assert!(!has_duplicates(&input[longest.clone()]));
+        assert_eq!(
+            find_distinct_window(input.as_bytes(), longest.len() + 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_distinct_profile() {
+        assert_eq!(
+            distinct_profile(b"abcabcdd", 3),
+            vec![1, 2, 3, 3, 3, 3, 3, 2]
+        );
+        assert_eq!(distinct_profile(b"abc", 0), vec![0, 0, 0]);
+        assert_eq!(distinct_profile(b"", 4), vec![]);
+
+        // a marker is wherever the whole window is distinct
+        let input = b"nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
+        let profile = distinct_profile(input, 4);
+        assert_eq!(
+            profile.iter().position(|&d| d == 4).map(|i| i + 1),
+            find_distinct_window(input, 4)
+        );
+
+        let mut out = vec![];
+        write_profile(&mut out, &distinct_profile(b"aab", 2)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "position,distinct\n1,1\n2,1\n3,2\n"
+        );
+    }
+
+    #[test]
+    fn test_lowercase_fast_path() {
+        for (letters, seed) in [(4, 1), (13, 2), (26, 3)] {
+            let input = bench::generate(2_000, letters, seed);
+            for w in [1, 3, 4, 5, 10, 14, 26, 27] {
+                let generic = find_any_window(input.as_bytes(), w);
+                assert_eq!(find_distinct_window(input.as_bytes(), w), generic);
+
+                // anything but a lowercase letter falls back to the generic
+                // search, wherever it shows up
+                for at in [0, 63, 64, 100, 1999] {
+                    let mut mixed = input.clone().into_bytes();
+                    mixed[at] = b'A';
+                    assert_eq!(
+                        find_distinct_window(&mixed, w),
+                        find_any_window(&mixed, w),
+                        "{} {} {}",
+                        letters,
+                        w,
+                        at
+                    );
+                }
+            }
+        }
+    }
+
+    /// Hands out at most `chunk` bytes per read, so markers straddle reads.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
     }
 
     #[test]
-    fn test_find_start_of_message() {
+    fn test_read_distinct_window() {
+        // multi-byte characters are just more bytes to the stream
+        let input = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjwééé".repeat(3);
+        for chunk in [1, 3, 7, 1000] {
+            for w in [0, 4, 14, 40] {
+                let reader = Trickle {
+                    data: input.as_bytes(),
+                    chunk,
+                };
+                assert_eq!(
+                    read_distinct_window(reader, w).unwrap(),
+                    find_distinct_window(input.as_bytes(), w).map(|i| i as u64)
+                );
+            }
+        }
+        assert_eq!(read_distinct_window("abcd".as_bytes(), 5).unwrap(), None);
     }
 }
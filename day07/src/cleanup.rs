@@ -0,0 +1,143 @@
+use crate::tree::Tree;
+
+/// The disk the tree lives on and how much free space an update needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Disk {
+    pub size: u64,
+    pub required: u64,
+}
+
+impl Default for Disk {
+    /// The numbers from the puzzle.
+    fn default() -> Self {
+        Disk {
+            size: 70_000_000,
+            required: 30_000_000,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Cleanup {
+    /// there is enough free space already
+    NotNeeded { free: u64 },
+    /// deleting this directory, the smallest that is big enough, frees
+    /// enough space. The root itself is never deleted.
+    Delete { path: String, size: u64 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// the files take more space than the disk has
+    TreeTooLarge { used: u64, disk: u64 },
+    /// not even an empty disk has the required space
+    RequiredTooLarge { required: u64, disk: u64 },
+    /// no directory below the root holds the bytes that have to go
+    NoDirectoryLargeEnough { needed: u64 },
+}
+
+/// How much has to be deleted from `tree` to get the required free space,
+/// 0 if there is enough already.
+pub fn to_free(tree: &Tree, disk: Disk) -> Result<u64, Error> {
+    let used = tree.size();
+    let free = disk.size.checked_sub(used).ok_or(Error::TreeTooLarge {
+        used,
+        disk: disk.size,
+    })?;
+    if disk.required > disk.size {
+        return Err(Error::RequiredTooLarge {
+            required: disk.required,
+            disk: disk.size,
+        });
+    }
+    Ok(disk.required.saturating_sub(free))
+}
+
+/// Picks the smallest directory whose deletion frees enough space.
+pub fn plan(tree: &Tree, disk: Disk) -> Result<Cleanup, Error> {
+    let needed = to_free(tree, disk)?;
+    if needed == 0 {
+        return Ok(Cleanup::NotNeeded {
+            free: disk.size - tree.size(),
+        });
+    }
+
+    let (path, _, dir) = tree
+        .dirs()
+        .filter(|(_, depth, dir)| *depth > 0 && dir.size() >= needed)
+        .min_by_key(|(_, _, dir)| dir.size())
+        .ok_or(Error::NoDirectoryLargeEnough { needed })?;
+    Ok(Cleanup::Delete {
+        path,
+        size: dir.size(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_tree;
+    use crate::tests::INPUT;
+
+    #[test]
+    fn test_plan() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        assert_eq!(to_free(&tree, Disk::default()), Ok(8381165));
+        assert_eq!(
+            plan(&tree, Disk::default()),
+            Ok(Cleanup::Delete {
+                path: "/d".to_string(),
+                size: 24933642
+            })
+        );
+
+        // only the whole tree is big enough, and the root stays
+        let disk = Disk {
+            size: 48381165,
+            required: 48381165,
+        };
+        assert_eq!(
+            plan(&tree, disk),
+            Err(Error::NoDirectoryLargeEnough { needed: 48381165 })
+        );
+    }
+
+    #[test]
+    fn test_not_needed() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        let disk = Disk {
+            size: 100_000_000,
+            required: 30_000_000,
+        };
+        assert_eq!(to_free(&tree, disk), Ok(0));
+        assert_eq!(plan(&tree, disk), Ok(Cleanup::NotNeeded { free: 51618835 }));
+    }
+
+    #[test]
+    fn test_errors() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        let disk = Disk {
+            size: 40_000_000,
+            required: 0,
+        };
+        assert_eq!(
+            plan(&tree, disk),
+            Err(Error::TreeTooLarge {
+                used: 48381165,
+                disk: 40_000_000
+            })
+        );
+
+        let disk = Disk {
+            size: 70_000_000,
+            required: 80_000_000,
+        };
+        assert_eq!(
+            plan(&tree, disk),
+            Err(Error::RequiredTooLarge {
+                required: 80_000_000,
+                disk: 70_000_000
+            })
+        );
+    }
+}
@@ -1,256 +1,263 @@
+mod cleanup;
+mod planner;
+mod report;
+mod tree;
+
+use cleanup::{Cleanup, Disk};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::alphanumeric0;
 use nom::combinator::map_res;
-use nom::multi::many1;
 use nom::sequence::preceded;
 use nom::sequence::tuple;
 use nom::Parser;
 use std::str::FromStr;
+use tree::{NodeId, Tree};
 
 #[derive(Debug)]
 enum LineItem<'a> {
-    LS,
-    CD(&'a str),
-    DIR(&'a str),
-    FILE(&'a str, u64),
+    Ls,
+    Cd(&'a str),
+    Dir(&'a str),
+    File(&'a str, u64),
 }
 
 fn number(input: &str) -> nom::IResult<&str, u64> {
     map_res(nom::character::complete::digit1, u64::from_str).parse(input)
 }
 
-fn parse_line<'a>(input: &'a str) -> nom::IResult<&'a str, LineItem<'a>> {
-    let ls_statement = |input| tag("$ ls").map(|_| LineItem::LS).parse(input);
+fn parse_line(input: &str) -> nom::IResult<&str, LineItem<'_>> {
+    let ls_statement = |input| tag("$ ls").map(|_| LineItem::Ls).parse(input);
     let cd_statement = |input| {
         preceded(tag("$ cd "), nom::character::complete::not_line_ending)
-            .map(|dir| LineItem::CD(dir))
+            .map(LineItem::Cd)
             .parse(input)
     };
 
     let entry_dir = |input| {
         preceded(tag("dir "), nom::character::complete::not_line_ending)
-            .map(LineItem::DIR)
+            .map(LineItem::Dir)
             .parse(input)
     };
 
     let entry_file = |input| {
         tuple((number, tag(" "), nom::character::complete::not_line_ending))
-            .map(|(size, _, name)| LineItem::FILE(name, size))
+            .map(|(size, _, name)| LineItem::File(name, size))
             .parse(input)
     };
 
     alt((ls_statement, cd_statement, entry_dir, entry_file)).parse(input)
 }
 
-type DirectoryT<'a> = Vec<Node<'a>>;
-
-#[derive(Debug, Clone)]
-enum Node<'a> {
-    File {
-        name: &'a str,
-        size: u64,
-    },
-    Directory {
-        name: &'a str,
-        nodes: DirectoryT<'a>,
-        size: u64,
-    },
+#[derive(Debug, PartialEq)]
+enum Error {
+    /// the line isn't a command or an entry of `ls`
+    Parse { line: usize },
+    /// a path goes through a file as if it were a directory
+    NotADirectory { line: usize, path: String },
+    /// `ls` lists a file where there is a directory
+    NotAFile { line: usize, path: String },
 }
 
-#[derive(Debug)]
-struct Tree<'a> {
-    nodes: DirectoryT<'a>,
-    size: u64,
+/// Replays a terminal session, the tree grows with every directory the
+/// session visits or lists.
+struct Session {
+    tree: Tree,
+    cwd: NodeId,
 }
 
-impl<'a> Tree<'a> {
-    fn walk<X>(&'a self, f: impl Fn(&Node) -> Option<X>) -> Vec<X> {
-        let mut stack = vec![self.nodes.iter()];
-        let mut values = vec![];
-
-        while stack.len() > 0 {
-            let mut iter = stack
-                .pop()
-                .expect("We just had an entry, where did it go?!");
-            let (value, s) = match iter.next() {
-                Some(s) => (f(s), s),
-                None => {
-                    continue;
-                }
-            };
-            stack.push(iter);
+impl Session {
+    fn new() -> Self {
+        let tree = Tree::default();
+        let cwd = tree.root();
+        Session { tree, cwd }
+    }
 
-            if let Some(v) = value {
-                values.push(v);
-            }
+    /// Follows `path` from the current directory, or from the root if it
+    /// starts with `/`. `..` at the root stays there, like a shell does, and
+    /// directories that weren't listed yet are created on the way.
+    fn cd(&mut self, path: &str) -> Result<(), String> {
+        let mut dir = if path.starts_with('/') {
+            self.tree.root()
+        } else {
+            self.cwd
+        };
 
-            match s {
-                Node::Directory { nodes, .. } => {
-                    stack.push(nodes.iter());
-                }
-                _ => {}
-            }
+        for name in path.split('/') {
+            dir = match name {
+                "" | "." => dir,
+                ".." => self.tree.get(dir).parent().unwrap_or(dir),
+                name => self
+                    .tree
+                    .ensure_dir(dir, name)
+                    .ok_or_else(|| self.tree.path(self.tree.child(dir, name).unwrap()))?,
+            };
         }
 
-        values
+        self.cwd = dir;
+        Ok(())
     }
-}
 
-// struct TreeIterator<'a, 'x> {
-//     stack: Vec<std::slice::Iter<'x, Node<'a>>>,
-// }
-//
-// impl<'a, 'x> TreeIterator<'a, 'x> {
-//     fn new(tree: &'x Tree<'a>) -> Self {
-// 	let it = tree.nodes.iter();
-// 	Self { stack: vec![ it ]}
-//     }
-// }
-//
-// impl<'a, 'x> Iterator for TreeIterator<'a, 'x> {
-//     type Item = &'x Node<'a>;
-//
-//     fn next(&self) -> Option<&'x Node<'a>> {
-// 	let x = match self.stack.get_mut(0) {
-// 	    None => return None,
-// 	    Some(x) => x,
-// 	};
-//
-// 	match x.next() {
-// 	    Some(Node::File { .. }) @ x => return x,
-// 	    Some(Node::Directory { .. }) @ x => return x,
-//
-// 	}
-//     }
-// }
-//
-// impl<'a, 'b> Tree<'a> {
-//     fn iter(&'b self) -> TreeIterator {
-// 	TreeIterator::new(self)
-//     }
-// }
-
-fn run<'a, I: Iterator<Item = LineItem<'a>>>(
-    level: usize,
-    it: &mut I,
-    tree: &mut DirectoryT<'a>,
-) -> (u64, bool) {
-    let mut total_size = 0;
-    while let Some(item) = it.next() {
+    fn run(&mut self, line: usize, item: LineItem) -> Result<(), Error> {
         match item {
-            LineItem::FILE(name, s) => {
-                total_size += s;
-                tree.push(Node::File { name, size: s });
+            LineItem::Ls => {}
+            LineItem::Cd(path) => {
+                self.cd(path)
+                    .map_err(|path| Error::NotADirectory { line, path })?;
             }
-            LineItem::DIR(subdir) => {
-                let nodes = DirectoryT::new();
-                tree.push(Node::Directory {
-                    name: subdir,
-                    nodes,
-                    size: 0,
-                });
+            // listing a directory again finds the same entries
+            LineItem::Dir(name) => {
+                if self.tree.ensure_dir(self.cwd, name).is_none() {
+                    let path = self.tree.path(self.tree.child(self.cwd, name).unwrap());
+                    return Err(Error::NotADirectory { line, path });
+                }
             }
-            LineItem::CD(subdir) if subdir == ".." => return (total_size, false),
-            LineItem::CD(subdir) if subdir == "/" && level != 0 => return (total_size, true),
-            LineItem::CD(subdir) if subdir == "/" && level == 0 => {}
-            LineItem::CD(subdir) => {
-                // find the node in the current tree nodes
-                let x = tree.iter_mut().find(|n| match n {
-                    Node::Directory { name, .. } if *name == subdir => true,
-                    _ => false,
-                });
-                match x {
-                    Some(Node::Directory {
-                        ref mut nodes,
-                        ref mut size,
-                        ..
-                    }) => {
-                        let (sub_size, return_to_root) = run(level + 1, it, nodes);
-                        total_size += sub_size;
-                        *size = sub_size;
-                        if return_to_root && level > 0 {
-                            return (total_size, true);
-                        }
-                    }
-                    None => panic!("Tried to enter directory that isn't known: {}", subdir),
-                    _ => panic!("the fuck?"),
+            LineItem::File(name, size) => {
+                if self.tree.ensure_file(self.cwd, name, size).is_none() {
+                    let path = self.tree.path(self.tree.child(self.cwd, name).unwrap());
+                    return Err(Error::NotAFile { line, path });
                 }
             }
-            _ => {}
         }
+        Ok(())
     }
-
-    (total_size, false)
 }
 
-fn parse_to_tree<'a>(input: &'a str) -> Tree<'a> {
-    let mut tree = Tree {
-        nodes: DirectoryT::new(),
-        size: 0,
-    };
-
-    let mut it = input.lines().map(|x| {
-        let (_, x) = parse_line(x).unwrap();
-        x
-    });
-
-    let (size, return_to_root) = run(0, &mut it, &mut tree.nodes);
-    if return_to_root {
-        panic!("return to root shouldn't bubble up");
+fn parse_to_tree(input: &str) -> Result<Tree, Error> {
+    let mut session = Session::new();
+    for (n, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let item = match nom::combinator::all_consuming(parse_line).parse(line) {
+            Ok((_, item)) => item,
+            Err(_) => return Err(Error::Parse { line: n + 1 }),
+        };
+        session.run(n + 1, item)?;
     }
-    tree.size = size;
 
-    tree
+    Ok(session.tree)
 }
 
-fn part1<'a>(input: &'a str) -> u64 {
-    let tree = parse_to_tree(input);
+/// The puzzle's limit for directories counted in part 1.
+const SMALL_DIR: u64 = 100_000;
 
-    let directories: Vec<u64> = tree.walk(|node| match node {
-        Node::Directory { name, size, .. } if *size <= 100000 => Some(*size),
-        _ => None,
-    });
+fn part1(input: &str, threshold: u64) -> u64 {
+    let tree = parse_to_tree(input).expect("invalid terminal session");
 
-    let sum = directories
-        .into_iter()
-        .sum();
-
-    sum
+    // like the puzzle's example, the root isn't counted
+    tree.dirs()
+        .filter(|(_, depth, _)| *depth > 0)
+        .map(|(_, _, dir)| dir.size())
+        .filter(|size| *size <= threshold)
+        .sum()
 }
 
-fn part2<'a>(input: &'a str) -> u64 {
-    let disk_size = 70000000;
-    let space_required = 30000000;
-
-    let tree = parse_to_tree(input);
-
-    let free = disk_size - tree.size;
-
-    println!("free: {}", free);
-
-    let have_to_free_at_least = space_required - free;
-    println!("to free: {}", have_to_free_at_least);
-
-    let directories: Vec<u64> = tree.walk(|node| match node {
-        Node::Directory { name, size, .. } if *size >= have_to_free_at_least => Some(*size),
-        _ => None,
-    });
-
-    let sum = directories.into_iter().min().unwrap();
+fn part2(input: &str, disk: Disk) -> Result<Cleanup, cleanup::Error> {
+    let tree = parse_to_tree(input).expect("invalid terminal session");
+    cleanup::plan(&tree, disk)
+}
 
-    sum
+fn usage() -> ! {
+    eprintln!(
+        "usage: day07 [--disk <size>] [--required <size>] [--threshold <size>]
+       day07 size <path>
+       day07 find <name>
+       day07 files <min size>
+       day07 empty
+       day07 tree | du | largest [<n>]
+       day07 plan bytes|items [exact|greedy]"
+    );
+    std::process::exit(1);
 }
 
 fn main() {
-    println!("part1: {}", part1(include_str!("input.txt")));
-    println!("part2: {}", part2(include_str!("input.txt")));
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut disk = Disk::default();
+    let mut threshold = SMALL_DIR;
+    while args.first().is_some_and(|a| a.starts_with("--")) {
+        let value = match args.get(1).map(|v| v.parse()) {
+            Some(Ok(value)) => value,
+            _ => usage(),
+        };
+        match args[0].as_str() {
+            "--disk" => disk.size = value,
+            "--required" => disk.required = value,
+            "--threshold" => threshold = value,
+            _ => usage(),
+        }
+        args.drain(..2);
+    }
+
+    if !args.is_empty() {
+        let tree = parse_to_tree(include_str!("input.txt")).expect("invalid terminal session");
+        match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["size", path] => match tree.lookup(path) {
+                Some(id) => println!("{}: {}", tree.path(id), tree.get(id).size()),
+                None => println!("{}: no such file or directory", path),
+            },
+            ["find", name] => tree
+                .iter()
+                .filter(|(_, _, node)| node.name() == name)
+                .for_each(|(path, _, _)| println!("{}", path)),
+            ["files", min] => {
+                let min: u64 = min.parse().unwrap_or_else(|_| usage());
+                tree.files()
+                    .filter(|(_, _, file)| file.size() >= min)
+                    .for_each(|(path, _, file)| println!("{} {}", file.size(), path));
+            }
+            // deepest first, so they can be removed one by one with rmdir
+            ["empty"] => tree
+                .iter_postorder()
+                .filter(|(_, depth, node)| *depth > 0 && node.is_dir() && node.size() == 0)
+                .for_each(|(path, _, _)| println!("{}", path)),
+            ["tree"] => print!("{}", report::listing(&tree)),
+            ["du"] => print!("{}", report::du(&tree)),
+            ["largest"] => print!("{}", report::largest_dirs(&tree, 10)),
+            ["largest", n] => {
+                let n = n.parse().unwrap_or_else(|_| usage());
+                print!("{}", report::largest_dirs(&tree, n));
+            }
+            ["plan", goal] | ["plan", goal, _] => {
+                let goal = match goal {
+                    "bytes" => planner::Goal::Bytes,
+                    "items" => planner::Goal::Items,
+                    _ => usage(),
+                };
+                let needed = match cleanup::to_free(&tree, disk) {
+                    Ok(needed) => needed,
+                    Err(e) => {
+                        println!("{:?}", e);
+                        return;
+                    }
+                };
+                let plan = match args.get(2).map(String::as_str) {
+                    None => planner::plan(&tree, needed, goal),
+                    Some("exact") => planner::exact(&tree, needed, goal),
+                    Some("greedy") => planner::greedy(&tree, needed, goal),
+                    Some(_) => usage(),
+                };
+                match plan {
+                    Some(plan) => println!("{}", plan),
+                    None => println!("cannot free {} bytes", needed),
+                }
+            }
+            _ => usage(),
+        }
+        return;
+    }
+
+    println!("part1: {}", part1(include_str!("input.txt"), threshold));
+    match part2(include_str!("input.txt"), disk) {
+        Ok(Cleanup::Delete { path, size }) => println!("part2: {} ({})", size, path),
+        Ok(Cleanup::NotNeeded { free }) => println!("part2: no deletion needed, {} free", free),
+        Err(e) => println!("part2: {:?}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const input: &'static str = r#"$ cd /
+    pub const INPUT: &str = r#"$ cd /
 $ ls
 dir a
 14848514 b.txt
@@ -276,35 +283,184 @@ $ ls
 
     #[test]
     fn test_example() {
-        assert_eq!(part1(input), 95437);
+        assert_eq!(part1(INPUT, SMALL_DIR), 95437);
+        assert_eq!(part1(INPUT, 584), 584);
+        assert_eq!(part1("$ cd /\n$ ls\n10 a\n", SMALL_DIR), 0);
+        assert_eq!(
+            part2(INPUT, Disk::default()),
+            Ok(Cleanup::Delete {
+                path: "/d".to_string(),
+                size: 24933642
+            })
+        );
     }
 
     #[test]
     fn test_example_tree() {
-        let t = parse_to_tree(input);
-        assert_eq!(t.size, 48381165);
-        assert_eq!(t.nodes.len(), 4);
-        assert!(matches!(t.nodes[0], Node::Directory { name: "a", .. }));
-        assert!(matches!(t.nodes[1], Node::File { name: "b.txt", .. }));
-        assert!(matches!(t.nodes[2], Node::File { name: "c.dat", .. }));
-        assert!(matches!(t.nodes[3], Node::Directory { name: "d", .. }));
-
-        let a = match &t.nodes[0] {
-            Node::Directory { nodes, .. } => nodes,
-            _ => panic!("foo"),
+        let t = parse_to_tree(INPUT).unwrap();
+        assert_eq!(t.size(), 48381165);
+
+        let names = |id| {
+            t.children(id)
+                .iter()
+                .map(|c| t.get(*c).name())
+                .collect::<Vec<_>>()
         };
+        assert_eq!(names(t.root()), vec!["a", "b.txt", "c.dat", "d"]);
+
+        let a = t.lookup("/a").unwrap();
+        assert!(t.get(a).is_dir());
+        assert_eq!(names(a), vec!["e", "f", "g", "h.lst"]);
+        assert_eq!(t.get(t.lookup("/a/e").unwrap()).size(), 584);
+        assert_eq!(t.get(t.lookup("/a").unwrap()).size(), 94853);
+        assert_eq!(t.get(t.lookup("/d/k").unwrap()).size(), 7214296);
+        assert!(!t.get(t.lookup("/b.txt").unwrap()).is_dir());
+        assert_eq!(t.lookup("/"), Some(t.root()));
+        assert_eq!(t.lookup("/a/x"), None);
+        assert_eq!(t.lookup("/b.txt/x"), None);
+    }
 
-        assert_eq!(a.len(), 4);
-        assert!(matches!(
-            a[0],
-            Node::Directory {
-                name: "e",
-                size: 584,
-                ..
-            }
-        ));
-        assert!(matches!(a[1], Node::File { name: "f", .. }));
-        assert!(matches!(a[2], Node::File { name: "g", .. }));
-        assert!(matches!(a[3], Node::File { name: "h.lst", .. }));
+    #[test]
+    fn test_parent_links() {
+        let t = parse_to_tree(INPUT).unwrap();
+        let i = t.lookup("/a/e/i").unwrap();
+        assert_eq!(t.path(i), "/a/e/i");
+        assert_eq!(t.path(t.root()), "/");
+
+        let e = t.get(i).parent().unwrap();
+        assert_eq!(t.get(e).name(), "e");
+        assert_eq!(t.get(t.get(e).parent().unwrap()).name(), "a");
+        assert_eq!(t.get(t.root()).parent(), None);
+    }
+
+    #[test]
+    fn test_owned_tree() {
+        // the tree doesn't borrow from the input
+        let input = INPUT.to_string();
+        let t = parse_to_tree(&input).unwrap();
+        drop(input);
+        assert_eq!(t.get(t.lookup("/d").unwrap()).name(), "d");
+    }
+
+    #[test]
+    fn test_cd() {
+        let t = parse_to_tree(concat!(
+            "$ cd a\n",
+            "$ cd ../../..\n",
+            "$ cd /a/e\n",
+            "$ ls\n",
+            "584 i\n",
+            "$ cd ./../../d/x\n",
+            "$ ls\n",
+            "1 y\n",
+            "$ cd /\n",
+            "$ cd ..\n",
+            "$ ls\n",
+            "10 z\n",
+        ))
+        .unwrap();
+
+        assert_eq!(t.size(), 595);
+        assert_eq!(t.get(t.lookup("/a").unwrap()).size(), 584);
+        assert_eq!(t.get(t.lookup("/a/e/i").unwrap()).size(), 584);
+        assert_eq!(t.get(t.lookup("/d/x/y").unwrap()).size(), 1);
+        assert_eq!(t.get(t.lookup("/z").unwrap()).size(), 10);
+    }
+
+    #[test]
+    fn test_repeated_ls() {
+        let input = format!("{}\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n1 new\n", INPUT);
+        let t = parse_to_tree(&input).unwrap();
+        let once = parse_to_tree(INPUT).unwrap();
+        assert_eq!(t.size(), once.size() + 1);
+        assert_eq!(t.children(t.root()).len(), 5);
+        assert_eq!(part1(&input, SMALL_DIR), part1(INPUT, SMALL_DIR));
+
+        // a file that changed in between is counted with its new size
+        let t = parse_to_tree("$ ls\n10 a\n$ cd /\n$ ls\n3 a\n").unwrap();
+        assert_eq!(t.size(), 3);
+    }
+
+    #[test]
+    fn test_session_errors() {
+        assert_eq!(
+            parse_to_tree("$ ls\n10 a\n$ cd a/b\n"),
+            Err(Error::NotADirectory {
+                line: 3,
+                path: "/a".to_string()
+            })
+        );
+        assert_eq!(
+            parse_to_tree("$ ls\ndir a\n10 a\n"),
+            Err(Error::NotAFile {
+                line: 3,
+                path: "/a".to_string()
+            })
+        );
+        assert_eq!(
+            parse_to_tree("$ ls\n10 a\n$ rm a\n"),
+            Err(Error::Parse { line: 3 })
+        );
+    }
+
+    #[test]
+    fn test_iter() {
+        let t = parse_to_tree(INPUT).unwrap();
+        let entries = |it: &mut dyn Iterator<Item = (String, usize, &tree::Node)>| {
+            it.map(|(path, depth, _)| format!("{} {}", depth, path))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            entries(&mut t.iter()),
+            vec![
+                "0 /",
+                "1 /a",
+                "2 /a/e",
+                "3 /a/e/i",
+                "2 /a/f",
+                "2 /a/g",
+                "2 /a/h.lst",
+                "1 /b.txt",
+                "1 /c.dat",
+                "1 /d",
+                "2 /d/j",
+                "2 /d/d.log",
+                "2 /d/d.ext",
+                "2 /d/k",
+            ]
+        );
+        assert_eq!(
+            entries(&mut t.iter_postorder()),
+            vec![
+                "3 /a/e/i",
+                "2 /a/e",
+                "2 /a/f",
+                "2 /a/g",
+                "2 /a/h.lst",
+                "1 /a",
+                "1 /b.txt",
+                "1 /c.dat",
+                "2 /d/j",
+                "2 /d/d.log",
+                "2 /d/d.ext",
+                "2 /d/k",
+                "1 /d",
+                "0 /",
+            ]
+        );
+        assert_eq!(
+            entries(&mut t.dirs()),
+            vec!["0 /", "1 /a", "2 /a/e", "1 /d"]
+        );
+        assert_eq!(t.files().count(), 10);
+        assert_eq!(
+            t.files().map(|(_, _, file)| file.size()).sum::<u64>(),
+            t.size()
+        );
+
+        // an empty directory is still a directory in post-order
+        let t = parse_to_tree("$ ls\ndir empty\n").unwrap();
+        assert_eq!(entries(&mut t.iter_postorder()), vec!["1 /empty", "0 /"]);
     }
 }
@@ -0,0 +1,307 @@
+//! Frees space by deleting several entries instead of a single directory.
+//!
+//! A plan is a set of files and directories, none of them inside another,
+//! that together free at least the needed number of bytes. The root itself
+//! is never deleted, only what is in it.
+
+use crate::tree::Tree;
+use std::fmt;
+
+/// Up to how many nodes `plan` searches for the best plan instead of a good
+/// one, the search takes exponential time in the worst case.
+pub const EXACT_LIMIT: usize = 32;
+
+/// What a plan should have as little of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    /// bytes deleted beyond the needed ones, fewer items break ties
+    Bytes,
+    /// items to delete, fewer bytes break ties
+    Items,
+}
+
+impl Goal {
+    /// Orders plans by the goal, smaller is better.
+    fn key(self, size: u64, items: usize) -> (u64, u64) {
+        match self {
+            Goal::Bytes => (size, items as u64),
+            Goal::Items => (items as u64, size),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    /// the absolute paths to delete, in the order of the tree
+    pub paths: Vec<String>,
+    /// how many bytes deleting them frees
+    pub size: u64,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.paths {
+            writeln!(f, "rm -r {}", path)?;
+        }
+        match self.paths.len() {
+            1 => write!(f, "# frees {} in 1 item", self.size),
+            n => write!(f, "# frees {} in {} items", self.size, n),
+        }
+    }
+}
+
+/// A node of the tree in pre-order, so its subtree is the range up to `end`.
+#[derive(Debug)]
+struct Entry {
+    path: String,
+    size: u64,
+    end: usize,
+    parent: Option<usize>,
+}
+
+/// Everything but the root in pre-order, `parent` and `end` index into it.
+fn entries(tree: &Tree) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    // the entries that are open at each depth, their subtree isn't done yet
+    let mut open: Vec<usize> = vec![];
+    for (path, depth, node) in tree.iter().skip(1) {
+        while open.len() >= depth {
+            let done = open.pop().unwrap();
+            entries[done].end = entries.len();
+        }
+        entries.push(Entry {
+            path,
+            size: node.size(),
+            end: 0,
+            parent: open.last().copied(),
+        });
+        open.push(entries.len() - 1);
+    }
+    for done in open {
+        entries[done].end = entries.len();
+    }
+    entries
+}
+
+/// Builds the plan from the chosen entries.
+fn to_plan(entries: &[Entry], mut chosen: Vec<usize>) -> Plan {
+    chosen.sort_unstable();
+    Plan {
+        size: chosen.iter().map(|&i| entries[i].size).sum(),
+        paths: chosen
+            .into_iter()
+            .map(|i| entries[i].path.clone())
+            .collect(),
+    }
+}
+
+/// The best plan for freeing `needed` bytes, `None` if even deleting
+/// everything isn't enough. Searches every plan, small trees only.
+pub fn exact(tree: &Tree, needed: u64, goal: Goal) -> Option<Plan> {
+    let entries = entries(tree);
+
+    // how much the entries from `i` on can free at most: the subtree of `i`
+    // followed by the forest after it
+    let mut available = vec![0; entries.len() + 1];
+    for i in (0..entries.len()).rev() {
+        available[i] = entries[i].size + available[entries[i].end];
+    }
+
+    struct Search<'a> {
+        entries: &'a [Entry],
+        available: &'a [u64],
+        needed: u64,
+        goal: Goal,
+        chosen: Vec<usize>,
+        best: Option<((u64, u64), Vec<usize>)>,
+    }
+
+    impl Search<'_> {
+        fn visit(&mut self, i: usize, freed: u64) {
+            if freed >= self.needed {
+                // anything more would only make the plan worse
+                let key = self.goal.key(freed, self.chosen.len());
+                if self.best.as_ref().is_none_or(|(best, _)| key < *best) {
+                    self.best = Some((key, self.chosen.clone()));
+                }
+                return;
+            }
+            if i == self.entries.len() || freed + self.available[i] < self.needed {
+                return;
+            }
+            // a plan from here takes at least one more item and frees at
+            // least the needed bytes
+            let bound = self.goal.key(self.needed, self.chosen.len() + 1);
+            if self.best.as_ref().is_some_and(|(best, _)| bound >= *best) {
+                return;
+            }
+
+            self.chosen.push(i);
+            self.visit(self.entries[i].end, freed + self.entries[i].size);
+            self.chosen.pop();
+            self.visit(i + 1, freed);
+        }
+    }
+
+    let mut search = Search {
+        entries: &entries,
+        available: &available,
+        needed,
+        goal,
+        chosen: vec![],
+        best: None,
+    };
+    if needed == 0 {
+        return Some(to_plan(&entries, vec![]));
+    }
+    search.visit(0, 0);
+    let (_, chosen) = search.best?;
+    Some(to_plan(&entries, chosen))
+}
+
+/// A good plan for freeing `needed` bytes, found in polynomial time, `None`
+/// if even deleting everything isn't enough.
+///
+/// It takes one entry at a time until enough is freed: for `Goal::Items` the
+/// smallest that frees the rest or else the largest, for `Goal::Bytes` the
+/// largest that doesn't free the rest yet or else the smallest. Entries that
+/// turn out not to be needed are dropped again, and the result is compared
+/// with deleting just the smallest single entry that is big enough.
+pub fn greedy(tree: &Tree, needed: u64, goal: Goal) -> Option<Plan> {
+    let entries = entries(tree);
+    let mut blocked = vec![false; entries.len()];
+    let mut chosen = vec![];
+    let mut freed = 0;
+
+    while freed < needed {
+        let rest = needed - freed;
+        let open = || (0..entries.len()).filter(|&i| !blocked[i]);
+        let finish = open()
+            .filter(|&i| entries[i].size >= rest)
+            .min_by_key(|&i| entries[i].size);
+        let next = match goal {
+            Goal::Items => finish.or_else(|| open().max_by_key(|&i| entries[i].size)),
+            Goal::Bytes => open()
+                .filter(|&i| entries[i].size < rest && entries[i].size > 0)
+                .max_by_key(|&i| entries[i].size)
+                .or(finish),
+        }?;
+
+        // neither what is in it nor what it is in can be taken any more
+        blocked[next..entries[next].end].fill(true);
+        let mut parent = entries[next].parent;
+        while let Some(p) = parent {
+            blocked[p] = true;
+            parent = entries[p].parent;
+        }
+        chosen.push(next);
+        freed += entries[next].size;
+    }
+
+    // the early, large picks may have become unnecessary
+    chosen.sort_unstable_by_key(|&i| std::cmp::Reverse(entries[i].size));
+    let mut k = 0;
+    while k < chosen.len() {
+        if freed - entries[chosen[k]].size >= needed {
+            freed -= entries[chosen[k]].size;
+            chosen.remove(k);
+        } else {
+            k += 1;
+        }
+    }
+
+    let single = (0..entries.len())
+        .filter(|&i| needed > 0 && entries[i].size >= needed)
+        .min_by_key(|&i| entries[i].size);
+    match single {
+        Some(i) if goal.key(entries[i].size, 1) < goal.key(freed, chosen.len()) => {
+            Some(to_plan(&entries, vec![i]))
+        }
+        _ => Some(to_plan(&entries, chosen)),
+    }
+}
+
+/// `exact` for trees of up to `EXACT_LIMIT` nodes, `greedy` for larger ones.
+pub fn plan(tree: &Tree, needed: u64, goal: Goal) -> Option<Plan> {
+    if tree.iter().count() <= EXACT_LIMIT {
+        exact(tree, needed, goal)
+    } else {
+        greedy(tree, needed, goal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_tree;
+    use crate::tests::INPUT;
+
+    fn paths(plan: &Plan) -> Vec<&str> {
+        plan.paths.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_entries() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        let entries = entries(&tree);
+        assert_eq!(entries.len(), 13);
+        assert_eq!(entries[0].path, "/a");
+        assert_eq!(entries[0].end, 6);
+        assert_eq!(entries[1].path, "/a/e");
+        assert_eq!(entries[1].end, 3);
+        assert_eq!(entries[2].parent, Some(1));
+        assert_eq!(entries[8].path, "/d");
+        assert_eq!(entries[8].parent, None);
+        assert_eq!(entries[12].end, 13);
+    }
+
+    #[test]
+    fn test_exact() {
+        let tree = parse_to_tree(INPUT).unwrap();
+
+        // the example of part 2 needs 8381165 bytes, c.dat comes closer
+        // than /d
+        for goal in [Goal::Bytes, Goal::Items] {
+            let plan = exact(&tree, 8381165, goal).unwrap();
+            assert_eq!(paths(&plan), ["/c.dat"]);
+            assert_eq!(plan.size, 8504156);
+        }
+
+        // /d alone is enough for 20M, two files waste less
+        let plan = exact(&tree, 20_000_000, Goal::Items).unwrap();
+        assert_eq!(paths(&plan), ["/d"]);
+        let plan = exact(&tree, 20_000_000, Goal::Bytes).unwrap();
+        assert_eq!(paths(&plan), ["/b.txt", "/d/d.ext"]);
+        assert_eq!(plan.size, 20474666);
+
+        assert_eq!(exact(&tree, 0, Goal::Bytes).unwrap().paths.len(), 0);
+        assert_eq!(exact(&tree, 48381166, Goal::Bytes), None);
+        assert_eq!(exact(&tree, 48381165, Goal::Items).unwrap().size, 48381165);
+    }
+
+    #[test]
+    fn test_greedy() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        for needed in [1, 584, 30000, 8381165, 20_000_000, 48381165] {
+            for goal in [Goal::Bytes, Goal::Items] {
+                let best = exact(&tree, needed, goal).unwrap();
+                let plan = greedy(&tree, needed, goal).unwrap();
+                assert!(plan.size >= needed, "{} {:?}", needed, goal);
+                assert!(
+                    goal.key(plan.size, plan.paths.len()) >= goal.key(best.size, best.paths.len())
+                );
+                // nothing in the plan is inside something else in it
+                for a in &plan.paths {
+                    for b in &plan.paths {
+                        assert!(a == b || !b.starts_with(&format!("{}/", a)));
+                    }
+                }
+            }
+        }
+        assert_eq!(greedy(&tree, 48381166, Goal::Items), None);
+        assert_eq!(
+            paths(&greedy(&tree, 8381165, Goal::Items).unwrap()),
+            ["/c.dat"]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::tree::{Node, Tree};
+
+/// The tree as the puzzle draws it, one `- name (dir)` or
+/// `- name (file, size=…)` line per node, indented by depth.
+pub fn listing(tree: &Tree) -> String {
+    tree.iter()
+        .map(|(_, depth, node)| {
+            let indent = "  ".repeat(depth);
+            match node {
+                Node::Directory { name, .. } => format!("{}- {} (dir)\n", indent, name),
+                Node::File { name, size, .. } => {
+                    format!("{}- {} (file, size={})\n", indent, name, size)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Like `du -a`: every file and directory with its size, directories after
+/// everything in them.
+pub fn du(tree: &Tree) -> String {
+    tree.iter_postorder()
+        .map(|(path, _, node)| format!("{}\t{}\n", node.size(), path))
+        .collect()
+}
+
+/// The `n` largest directories, largest first, with human readable sizes.
+pub fn largest_dirs(tree: &Tree, n: usize) -> String {
+    let mut dirs: Vec<_> = tree.dirs().collect();
+    dirs.sort_by_key(|(_, _, dir)| std::cmp::Reverse(dir.size()));
+    dirs.into_iter()
+        .take(n)
+        .map(|(path, _, dir)| format!("{:>7} {}\n", human_size(dir.size()), path))
+        .collect()
+}
+
+/// A size in bytes the way `du -h` shows it, in powers of 1024.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+
+    // round to what is shown before picking the unit, so 1048575 is 1.0M
+    // and not 1024K
+    let rounded = |size: f64| {
+        if size < 9.95 {
+            (size * 10.0).round() / 10.0
+        } else {
+            size.round()
+        }
+    };
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while rounded(size) >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 9.95 {
+        format!("{:.1}{}", size, UNITS[unit])
+    } else {
+        format!("{:.0}{}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_tree;
+    use crate::tests::INPUT;
+
+    #[test]
+    fn test_listing() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        assert_eq!(
+            listing(&tree),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+"
+        );
+    }
+
+    #[test]
+    fn test_du() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        let du = du(&tree);
+        let lines: Vec<_> = du.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0], "584\t/a/e/i");
+        assert_eq!(lines[1], "584\t/a/e");
+        assert_eq!(lines[5], "94853\t/a");
+        assert_eq!(lines[13], "48381165\t/");
+    }
+
+    #[test]
+    fn test_largest_dirs() {
+        let tree = parse_to_tree(INPUT).unwrap();
+        assert_eq!(
+            largest_dirs(&tree, 3),
+            "    46M /\n    24M /d\n    93K /a\n"
+        );
+        assert_eq!(largest_dirs(&tree, 10).lines().count(), 4);
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0B");
+        assert_eq!(human_size(1023), "1023B");
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(94853), "93K");
+        assert_eq!(human_size(70_000_000), "67M");
+        assert_eq!(human_size(5 << 30), "5.0G");
+        assert_eq!(human_size(u64::MAX), "16E");
+        assert_eq!(human_size(1048575), "1.0M");
+        assert_eq!(human_size(1048063), "1023K");
+        assert_eq!(human_size(10188), "9.9K");
+        assert_eq!(human_size(10189), "10K");
+    }
+}
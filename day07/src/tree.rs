@@ -0,0 +1,248 @@
+/// Addresses a node in the arena of its `Tree`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    File {
+        name: String,
+        size: u64,
+        parent: NodeId,
+    },
+    Directory {
+        name: String,
+        nodes: Vec<NodeId>,
+        /// the total size of all files below the directory
+        size: u64,
+        /// `None` only for the root
+        parent: Option<NodeId>,
+    },
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Node::File { name, .. } | Node::Directory { name, .. } => name,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        match self {
+            Node::File { size, .. } | Node::Directory { size, .. } => *size,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        match self {
+            Node::File { parent, .. } => Some(*parent),
+            Node::Directory { parent, .. } => *parent,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Node::Directory { .. })
+    }
+}
+
+/// A file system, all nodes live in one arena and refer to each other by
+/// their `NodeId`. The root directory is always there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Tree {
+            nodes: vec![Node::Directory {
+                name: "/".to_string(),
+                nodes: vec![],
+                size: 0,
+                parent: None,
+            }],
+        }
+    }
+}
+
+impl Tree {
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn get(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// The total size of all files.
+    pub fn size(&self) -> u64 {
+        self.get(self.root()).size()
+    }
+
+    /// The entries of a directory, nothing for files.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match self.get(id) {
+            Node::Directory { nodes, .. } => nodes,
+            Node::File { .. } => &[],
+        }
+    }
+
+    /// The entry of directory `dir` called `name`.
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        self.children(dir)
+            .iter()
+            .copied()
+            .find(|id| self.get(*id).name() == name)
+    }
+
+    /// Finds a node by its absolute path like `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self.root(), |dir, name| self.child(dir, name))
+    }
+
+    /// The absolute path of a node, `/` for the root.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut current = id;
+        while let Some(parent) = self.get(current).parent() {
+            names.push(self.get(current).name());
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    /// The directory `name` in `parent`, created if it isn't there yet.
+    /// `None` if there is a file of that name.
+    pub fn ensure_dir(&mut self, parent: NodeId, name: &str) -> Option<NodeId> {
+        match self.child(parent, name) {
+            Some(id) if self.get(id).is_dir() => Some(id),
+            Some(_) => None,
+            None => Some(self.add_dir(parent, name)),
+        }
+    }
+
+    /// The file `name` in `parent` of the given size, created if it isn't
+    /// there yet. A file that is seen again takes the new size. `None` if
+    /// there is a directory of that name.
+    pub fn ensure_file(&mut self, parent: NodeId, name: &str, size: u64) -> Option<NodeId> {
+        match self.child(parent, name) {
+            Some(id) => match &mut self.nodes[id.0] {
+                Node::File { size: old, .. } => {
+                    let old = std::mem::replace(old, size);
+                    self.grow(parent, old, size);
+                    Some(id)
+                }
+                Node::Directory { .. } => None,
+            },
+            None => Some(self.add_file(parent, name, size)),
+        }
+    }
+
+    /// Adds an empty directory to `parent`.
+    fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        self.add(
+            parent,
+            Node::Directory {
+                name: name.to_string(),
+                nodes: vec![],
+                size: 0,
+                parent: Some(parent),
+            },
+        )
+    }
+
+    /// Adds a file to `parent`, its size counts towards all directories
+    /// above it.
+    fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> NodeId {
+        let id = self.add(
+            parent,
+            Node::File {
+                name: name.to_string(),
+                size,
+                parent,
+            },
+        );
+        self.grow(parent, 0, size);
+        id
+    }
+
+    /// Accounts for files of `dir` changing their size from `old` to `new`.
+    fn grow(&mut self, dir: NodeId, old: u64, new: u64) {
+        let mut current = Some(dir);
+        while let Some(dir) = current {
+            if let Node::Directory { size, .. } = &mut self.nodes[dir.0] {
+                *size = *size - old + new;
+            }
+            current = self.get(dir).parent();
+        }
+    }
+
+    fn add(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(node);
+        match &mut self.nodes[parent.0] {
+            Node::Directory { nodes, .. } => nodes.push(id),
+            Node::File { name, .. } => panic!("{} is a file, not a directory", name),
+        }
+        id
+    }
+
+    /// Every node with its path and depth, the root first at depth 0 and
+    /// every directory before its entries.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            tree: self,
+            stack: vec![(self.root(), 0, false)],
+            postorder: false,
+        }
+    }
+
+    /// Like `iter`, but every directory comes after its entries.
+    pub fn iter_postorder(&self) -> Iter<'_> {
+        Iter {
+            postorder: true,
+            ..self.iter()
+        }
+    }
+
+    pub fn dirs(&self) -> impl Iterator<Item = (String, usize, &Node)> {
+        self.iter().filter(|(_, _, node)| node.is_dir())
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (String, usize, &Node)> {
+        self.iter().filter(|(_, _, node)| !node.is_dir())
+    }
+}
+
+/// Walks a `Tree` depth first, see `Tree::iter`.
+#[derive(Debug, Clone)]
+pub struct Iter<'t> {
+    tree: &'t Tree,
+    // the nodes still to visit with their depth, and whether their entries
+    // are already on the stack
+    stack: Vec<(NodeId, usize, bool)>,
+    postorder: bool,
+}
+
+impl<'t> Iterator for Iter<'t> {
+    type Item = (String, usize, &'t Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (id, depth, expanded) = self.stack.pop()?;
+            let children = self.tree.children(id);
+            if self.postorder && !expanded && !children.is_empty() {
+                self.stack.push((id, depth, true));
+            }
+            if !expanded {
+                self.stack
+                    .extend(children.iter().rev().map(|c| (*c, depth + 1, false)));
+            }
+            if !self.postorder || expanded || children.is_empty() {
+                return Some((self.tree.path(id), depth, self.tree.get(id)));
+            }
+        }
+    }
+}